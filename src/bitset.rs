@@ -2,7 +2,8 @@ use std::collections::HashSet;
 use std::fmt;
 
 use crate::heuristic::{Heuristic, HeuristicDomainOperations};
-use rand::prelude::*;
+use rand::rngs::StdRng;
+use rand::Rng;
 
 pub trait DomainOperations {
     type Item;
@@ -35,7 +36,7 @@ impl DomainOperations for BitSet {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
 pub struct BitSet {
     set: u64,
 }
@@ -45,9 +46,10 @@ pub struct BitIter {
     current: BitSet,
 }
 
-pub struct HeuristicBitIter {
+pub struct HeuristicBitIter<'a> {
     current: BitSet,
     heuristic: Heuristic,
+    rng: &'a mut StdRng,
 }
 
 impl BitSet {
@@ -63,22 +65,39 @@ impl BitSet {
         self.set &= !(1 << n as u64);
     }
 
+    pub fn contains(self, n: u32) -> bool {
+        self.set & (1 << n as u64) != 0
+    }
+
+    pub fn union(self, other: BitSet) -> Self {
+        Self {
+            set: self.set | other.set,
+        }
+    }
+
+    pub fn intersection(self, other: BitSet) -> Self {
+        Self {
+            set: self.set & other.set,
+        }
+    }
+
     pub fn len(self) -> usize {
         self.set.count_ones() as usize
     }
 
     pub fn current(self) -> u32 {
-        (self.set.trailing_zeros() & !64 as u32) as u32
+        self.set.trailing_zeros() & !64
     }
 
     pub fn iter(self) -> BitIter {
         BitIter { current: self }
     }
 
-    pub fn iter_h(self, heuristic: &Heuristic) -> HeuristicBitIter {
+    pub fn iter_h<'a>(self, heuristic: &Heuristic, rng: &'a mut StdRng) -> HeuristicBitIter<'a> {
         HeuristicBitIter {
             current: self,
             heuristic: heuristic.clone(),
+            rng,
         }
     }
 
@@ -86,8 +105,7 @@ impl BitSet {
         self.len() == 0
     }
 
-    pub fn random(self) -> u32 {
-        let mut rng = thread_rng();
+    pub fn random(self, rng: &mut StdRng) -> u32 {
         let len = self.len();
         if len == 0 {
             return 0;
@@ -116,7 +134,7 @@ impl Iterator for BitIter {
     }
 }
 
-impl Iterator for HeuristicBitIter {
+impl Iterator for HeuristicBitIter<'_> {
     type Item = u32;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -124,7 +142,7 @@ impl Iterator for HeuristicBitIter {
             return None;
         }
 
-        let next = HeuristicDomainOperations::next(&self.current, &self.heuristic);
+        let next = HeuristicDomainOperations::next(&self.current, &self.heuristic, self.rng);
         self.current.remove(next);
 
         Some(next)
@@ -1,19 +1,37 @@
+mod cnf;
+mod conflict;
 mod empty_slots;
+mod hint;
+mod search;
+mod techniques;
+mod trail;
+mod validation;
+
+pub use conflict::{Conflict, Unit};
+pub use hint::Hint;
+pub use search::Solutions;
+pub use techniques::{Step, Technique};
+pub use validation::Validation;
 
 use super::bitset::BitSet;
 
 use colored::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
+use crate::error::ParseError;
 use crate::heuristic::{Heuristic, HeuristicDomainOperations};
-use empty_slots::EmptySlots;
+use crate::solver::{Method, SolutionCount, SolveResult};
+use search::Search;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
 use std::iter::FromIterator;
+use std::str::FromStr;
+use std::time::Instant;
+use trail::Trail;
 
 pub type Domain = BitSet;
 
-static SOLVED_CHARACTER: char = '\u{2714}';
-static UNSOLVED_CHARACTER: char = '\u{2718}';
-
 #[derive(Debug, Clone)]
 pub struct Sudoku {
     pub board: Vec<u32>,
@@ -23,33 +41,252 @@ pub struct Sudoku {
     pub board_rows: Vec<Vec<usize>>,
     pub board_cols: Vec<Vec<usize>>,
     pub board_squares: Vec<Vec<usize>>,
+    /// Cells sharing a row, column or square with each cell, in increasing order
+    pub board_peers: Vec<Vec<usize>>,
     pub domain_size: usize,
+    pub box_rows: usize,
+    pub box_cols: usize,
     pub domain: Domain,
 }
 
+/// The biggest side of the board, every value is written as a single character in base 36, up to
+/// `Z` for 35.
+pub const MAX_SIZE: usize = 35;
+
+/// Given the side of the board, returns the dimensions (rows, cols) of its boxes.
+///
+/// The boxes are kept as close to square as possible, so a 9x9 board gets 3x3 boxes and a 6x6
+/// board gets 2x3 boxes.
+pub fn box_dimensions(size: usize) -> (usize, usize) {
+    let rows = (1..=size)
+        .take_while(|r| r * r <= size)
+        .filter(|r| size.is_multiple_of(*r))
+        .last()
+        .unwrap_or(1);
+    (rows, size / rows.max(1))
+}
+
+/// Maps a cell value to its character, values above 9 are written as letters (A = 10).
+pub fn value_to_char(value: u32) -> char {
+    match value {
+        0 => '.',
+        v => std::char::from_digit(v, 36)
+            .map(|c| c.to_ascii_uppercase())
+            .unwrap_or('?'),
+    }
+}
+
+/// Maps a character to a cell value, `.` and `0` mean an empty cell.
+pub fn char_to_value(c: char) -> Option<u32> {
+    match c {
+        '.' => Some(0),
+        c => c.to_digit(36),
+    }
+}
+
 impl Sudoku {
     pub fn from_flattened(s: &str) -> Self {
         let v: Vec<(usize, u32)> = s
             .chars()
             .enumerate()
-            .map(|(i, c)| (i, c.to_digit(36).unwrap_or_default()))
+            .map(|(i, c)| (i, char_to_value(c).unwrap_or_default()))
             .collect();
 
         Self::from_iter(v)
     }
+
+    /// Parses the flattened board, inferring its geometry from the number of cells.
+    ///
+    /// Unlike `from_flattened`, every character has to be a value fitting the board or an empty
+    /// cell (`.` or `0`), and the givens cannot conflict with each other.
+    pub fn try_from_flattened(s: &str) -> Result<Self, ParseError> {
+        Self::try_from_flattened_unchecked(s)?.check_givens()
+    }
+
+    /// Parses the flattened board like `try_from_flattened`, but lets the values conflict with
+    /// each other, so that the board can be validated afterwards.
+    pub fn try_from_flattened_unchecked(s: &str) -> Result<Self, ParseError> {
+        let s = s.trim();
+        let len = s.chars().count();
+        if len == 0 {
+            return Err(ParseError::Empty);
+        }
+        let size = (len as f64).sqrt().round() as usize;
+        if size > MAX_SIZE || size * size != len {
+            let expected = size.clamp(1, MAX_SIZE);
+            return Err(ParseError::InvalidLength {
+                found: len,
+                expected: expected * expected,
+            });
+        }
+
+        let (box_rows, box_cols) = box_dimensions(size);
+        Self::parse_with_box(box_rows, box_cols, s)
+    }
+
+    /// Parses the flattened board with the given box dimensions.
+    pub fn try_with_box(box_rows: usize, box_cols: usize, s: &str) -> Result<Self, ParseError> {
+        Self::parse_with_box(box_rows, box_cols, s)?.check_givens()
+    }
+
+    fn parse_with_box(box_rows: usize, box_cols: usize, s: &str) -> Result<Self, ParseError> {
+        let s = s.trim();
+        let size = box_rows * box_cols;
+        let len = s.chars().count();
+        if len == 0 {
+            return Err(ParseError::Empty);
+        }
+        if size == 0 || size > MAX_SIZE || len != size * size {
+            return Err(ParseError::InvalidLength {
+                found: len,
+                expected: size * size,
+            });
+        }
+
+        let values = s
+            .chars()
+            .enumerate()
+            .map(|(position, character)| match char_to_value(character) {
+                Some(value) if value as usize > size => Err(ParseError::ValueOutOfRange {
+                    position,
+                    character,
+                    value,
+                    max: size as u32,
+                }),
+                Some(value) => Ok(value),
+                None => Err(ParseError::InvalidCharacter {
+                    position,
+                    character,
+                }),
+            })
+            .collect::<Result<Vec<u32>, ParseError>>()?;
+
+        Ok(Self::with_box(box_rows, box_cols, values))
+    }
+
+    fn check_givens(self) -> Result<Self, ParseError> {
+        let conflicts = self.conflicts();
+        if conflicts.is_empty() {
+            Ok(self)
+        } else {
+            Err(ParseError::ConflictingGivens(conflicts))
+        }
+    }
+
+    /// Builds the board with the given box dimensions, the board side is `box_rows * box_cols`.
+    ///
+    /// Missing cells are filled with 0 and the surplus ones are dropped.
+    pub fn with_box(box_rows: usize, box_cols: usize, values: Vec<u32>) -> Self {
+        let size = box_rows * box_cols;
+        let mut board = values;
+        board.resize(size * size, 0);
+
+        let empty_slots: Vec<usize> = board
+            .iter()
+            .enumerate()
+            .filter(|&(_, &val)| val == 0)
+            .map(|(idx, _)| idx)
+            .collect();
+
+        let board_coords: Vec<(usize, usize)> = (0..board.len())
+            .map(|idx| (idx / size, idx % size))
+            .collect();
+
+        let mut sudoku = Self {
+            domains: board.iter().map(|_| Domain::new()).collect(),
+            board,
+            empty_slots,
+            board_coords,
+            board_rows: Vec::new(),
+            board_cols: Vec::new(),
+            board_squares: Vec::new(),
+            board_peers: Vec::new(),
+            domain_size: size,
+            box_rows,
+            box_cols,
+            domain: Domain::new(),
+        };
+
+        // what indices are in each row?
+        sudoku.board_rows = (0..size)
+            .map(|x| (0..size).map(|y| sudoku.index(x, y)).collect())
+            .collect();
+        // what indices are in each col?
+        sudoku.board_cols = (0..size)
+            .map(|x| (0..size).map(|y| sudoku.index(y, x)).collect())
+            .collect();
+        // what indices are in each square?
+        sudoku.board_squares = {
+            let mut map = vec![vec![0usize; size]; size];
+            (0..size).for_each(|x| {
+                (0..size).for_each(|y| {
+                    map[sudoku.square(x, y)][sudoku.index_in_block(x, y)] = sudoku.index(x, y);
+                })
+            });
+            map
+        };
+        // which cells share a unit with each one?
+        sudoku.board_peers = (0..sudoku.board.len())
+            .map(|idx| {
+                let (row, col) = sudoku.board_coords[idx];
+                let square = sudoku.square(row, col);
+                let mut peers: Vec<usize> = sudoku.board_rows[row]
+                    .iter()
+                    .chain(sudoku.board_cols[col].iter())
+                    .chain(sudoku.board_squares[square].iter())
+                    .copied()
+                    .filter(|&peer| peer != idx)
+                    .collect();
+                peers.sort_unstable();
+                peers.dedup();
+                peers
+            })
+            .collect();
+
+        sudoku
+    }
+
+    /// Returns every row, column and square of the board together with its cells.
+    pub fn units(&self) -> impl Iterator<Item = (Unit, &Vec<usize>)> {
+        self.board_rows
+            .iter()
+            .enumerate()
+            .map(|(n, cells)| (Unit::Row(n), cells))
+            .chain(
+                self.board_cols
+                    .iter()
+                    .enumerate()
+                    .map(|(n, cells)| (Unit::Column(n), cells)),
+            )
+            .chain(
+                self.board_squares
+                    .iter()
+                    .enumerate()
+                    .map(|(n, cells)| (Unit::Square(n), cells)),
+            )
+    }
+
+    /// Returns the domain containing every value that can be placed on this board.
+    pub fn full_domain(&self) -> Domain {
+        let mut domain = Domain::new();
+        (1..=self.domain_size as u32).for_each(|val| domain.insert(val));
+        domain
+    }
+
     pub fn print_board(&self, current: usize) {
         let (row_id, col_id) = self.board_coords[current];
 
         self.board_rows.iter().for_each(|row| {
             row.iter().for_each(|&idx| {
+                let value = value_to_char(self.board[idx]).to_string();
                 if idx == current {
-                    print!(" {}", format!("{}", self.board[idx]).red());
+                    print!(" {}", value.red());
                 } else if self.board_rows[row_id].contains(&idx)
                     || self.board_cols[col_id].contains(&idx)
                 {
-                    print!(" {}", format!("{}", self.board[idx]).blue());
+                    print!(" {}", value.blue());
                 } else {
-                    print!(" {}", self.board[idx]);
+                    print!(" {}", value);
                 }
             });
             println!();
@@ -57,42 +294,99 @@ impl Sudoku {
     }
 
     // given coordinates, returns actual index in the array
-    pub fn index(row: usize, col: usize) -> usize {
-        row * 9 + col
+    pub fn index(&self, row: usize, col: usize) -> usize {
+        row * self.domain_size + col
     }
 
     // given coordinates, return block number
-    pub fn square(row: usize, col: usize) -> usize {
-        let r = row / 3;
-        let c = col / 3;
-        r * 3 + c
+    pub fn square(&self, row: usize, col: usize) -> usize {
+        // there are `box_rows` blocks in every band of rows
+        let r = row / self.box_rows;
+        let c = col / self.box_cols;
+        r * self.box_rows + c
     }
 
     // given coordinates, return index inside its block
-    pub fn index_in_block(row: usize, col: usize) -> usize {
-        let r = row % 3;
-        let c = col % 3;
-        r * 3 + c
+    pub fn index_in_block(&self, row: usize, col: usize) -> usize {
+        let r = row % self.box_rows;
+        let c = col % self.box_cols;
+        r * self.box_cols + c
+    }
+
+    /// Checks whether every cell is filled and no constraint is broken.
+    pub fn is_solved(&self) -> bool {
+        self.validate().is_valid()
+    }
+
+    /// Checks the whole board, reporting the empty cells and every broken constraint.
+    pub fn validate(&self) -> Validation {
+        Validation {
+            conflicts: self.conflicts(),
+            empty_cells: self
+                .board
+                .iter()
+                .enumerate()
+                .filter(|&(_, &v)| v == 0)
+                .map(|(idx, _)| idx)
+                .collect(),
+            changed_givens: Vec::new(),
+        }
+    }
+
+    /// Checks the board as a solution of the given puzzle, its givens have to be kept.
+    pub fn validate_solution_of(&self, puzzle: &Sudoku) -> Validation {
+        let mut validation = self.validate();
+        validation.changed_givens = puzzle
+            .board
+            .iter()
+            .zip(self.board.iter())
+            .enumerate()
+            .filter(|&(_, (&given, &value))| given != 0 && given != value)
+            .map(|(idx, _)| idx)
+            .collect();
+        validation
+    }
+
+    /// Returns every value placed more than once in a row, column or square.
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        for (unit, cells) in self.units() {
+            let mut seen: HashMap<u32, Vec<usize>> = HashMap::new();
+            cells
+                .iter()
+                .filter(|&&idx| self.board[idx] != 0)
+                .for_each(|&idx| seen.entry(self.board[idx]).or_default().push(idx));
+
+            let mut repeated = seen
+                .into_iter()
+                .filter(|(_, cells)| cells.len() > 1)
+                .map(|(value, cells)| Conflict { unit, value, cells })
+                .collect::<Vec<_>>();
+            repeated.sort_unstable_by_key(|conflict| conflict.value);
+            conflicts.extend(repeated);
+        }
+
+        conflicts
     }
 
     pub fn set_number(&mut self, row: usize, col: usize, value: u32) -> bool {
-        let index = Self::index(row, col);
+        let index = self.index(row, col);
         self.board[index] = value;
         true
     }
 
     pub fn get_domain(&self, row: usize, col: usize) -> BitSet {
-        let index = Self::index(row, col);
+        let index = self.index(row, col);
         self.domains[index]
     }
 
     pub fn set_domain(&mut self, row: usize, col: usize, domain: BitSet) {
-        let index = Self::index(row, col);
+        let index = self.index(row, col);
         self.domains[index] = domain;
     }
 
     pub fn is_valid(&self, row: usize, col: usize, value: u32) -> bool {
-        let index = Self::index(row, col);
+        let index = self.index(row, col);
         // C1 - there cannot be any duplicate in the row
         for i in &self.board_rows[row] {
             if i == &index {
@@ -114,7 +408,7 @@ impl Sudoku {
         }
 
         // C3 - There cannot be any duplicate in the square
-        let square = Self::square(row, col);
+        let square = self.square(row, col);
         for i in &self.board_squares[square] {
             if i == &index {
                 continue;
@@ -126,222 +420,234 @@ impl Sudoku {
         true
     }
 
-    pub fn find_solution(&mut self, row: usize, col: usize, heuristic: &Heuristic) -> Option<u32> {
+    pub fn find_solution(
+        &mut self,
+        row: usize,
+        col: usize,
+        heuristic: &Heuristic,
+        rng: &mut StdRng,
+    ) -> Option<u32> {
+        let index = self.index(row, col);
         let mut current_domain = self.get_domain(row, col);
-        let mut value = HeuristicDomainOperations::next(&current_domain, heuristic);
 
-        while value != 0 {
+        while let Some(value) = self.next_value(index, current_domain, heuristic, rng) {
             current_domain.remove(value);
             if self.is_valid(row, col, value) {
                 self.set_domain(row, col, current_domain);
                 return Some(value);
             }
-            value = HeuristicDomainOperations::next(&current_domain, heuristic);
         }
         None
     }
 
-    pub fn solve(
-        &mut self,
-        value_heuristic: &Heuristic,
-        slot_heuristic: &Heuristic,
-    ) -> (char, u64) {
-        let domain_holder = self.domains.clone();
-        let mut backtrack_counter = 0u64;
-        let mut slots = EmptySlots::from(self.empty_slots.iter());
-        slots.set_heuristic(slot_heuristic);
-
-        while let Some(slot) = slots.next(&self.domains) {
-            // println!("============================================");
-            // self.print_board(slot);
-
-            let (row, col) = self.board_coords[slot];
-            match self.find_solution(row, col, value_heuristic) {
-                // match self.find_solution(row, col) {
-                Some(solution) => {
-                    // dbg!(solution);
-                    self.set_number(row, col, solution);
-                }
-                None => {
-                    // no solution found, reset cell and go back to previous one
-                    self.set_number(row, col, 0);
-                    self.set_domain(row, col, domain_holder[slot]);
-                    backtrack_counter += 1;
-                    dbg!(backtrack_counter);
-                    if slots.backtrack().is_none() {
-                        println!("No Solution");
-                        return (UNSOLVED_CHARACTER, backtrack_counter);
-                    }
-                }
+    /// Picks the next value to try in the cell out of the given domain.
+    ///
+    /// Unlike `HeuristicDomainOperations::next`, the value heuristics can look at the rest of the
+    /// board here.
+    pub fn next_value(
+        &self,
+        idx: usize,
+        domain: Domain,
+        heuristic: &Heuristic,
+        rng: &mut StdRng,
+    ) -> Option<u32> {
+        if domain.is_empty() {
+            return None;
+        }
+
+        match heuristic {
+            Heuristic::LeastConstrainingValue => {
+                let peers = self.peers(idx);
+                domain.iter().min_by_key(|&value| {
+                    // how many options would the value take from the empty peers
+                    peers
+                        .iter()
+                        .filter(|&&peer| self.board[peer] == 0)
+                        .filter(|&&peer| self.domains[peer].iter().any(|v| v == value))
+                        .count()
+                })
             }
+            _ => Some(HeuristicDomainOperations::next(&domain, heuristic, rng)),
         }
+    }
 
-        (SOLVED_CHARACTER, backtrack_counter)
+    /// Returns the cells sharing a row, column or square with the given one.
+    pub fn peers(&self, idx: usize) -> &[usize] {
+        &self.board_peers[idx]
     }
 
-    fn backtrack(
+    pub fn solve(
         &mut self,
-        version: (Vec<u32>, Vec<Domain>, usize),
-        value: u32,
-    ) -> Result<usize, usize> {
-        self.board = version.0;
-        self.domains = version.1;
-        self.board[self.empty_slots[version.2]] = 0;
-        self.domains[self.empty_slots[version.2]].remove(value);
-        if self.domains[self.empty_slots[version.2]].is_empty() {
-            return Err(version.2);
-        }
+        value_heuristic: &Heuristic,
+        slot_heuristic: &Heuristic,
+    ) -> SolveResult {
+        let method = Method::Backtracking {
+            value_heuristic: value_heuristic.clone(),
+            slot_heuristic: slot_heuristic.clone(),
+        };
+        self.solve_with(&method, StdRng::from_entropy())
+    }
 
-        Ok(version.2)
+    pub fn solve_fc(
+        &mut self,
+        value_heuristic: &Heuristic,
+        slot_heuristic: &Heuristic,
+    ) -> SolveResult {
+        let method = Method::ForwardChecking {
+            value_heuristic: value_heuristic.clone(),
+            slot_heuristic: slot_heuristic.clone(),
+        };
+        self.solve_with(&method, StdRng::from_entropy())
     }
 
-    pub fn solve_fc(&mut self, heuristic: &Heuristic) -> (char, u64) {
-        let mut i: usize = 0;
-        let mut backtrack_counter = 0u64;
-        let mut versions: Vec<(Vec<u32>, Vec<Domain>, usize)> = Vec::new();
-        while i < self.empty_slots.len() {
-            versions.push((self.board.clone(), self.domains.clone(), i));
-            let (row, col) = self.board_coords[self.empty_slots[i]];
-            // if value of cell in the index is not 0, then it was previously set during forward
-            // checking.
-            if self.board[Self::index(row, col)] != 0 {
-                i += 1;
-                continue;
-            }
+    /// Solves the board with the given method, the random heuristics draw from `rng`, so the
+    /// same seed always gives the same search.
+    pub fn solve_with(&mut self, method: &Method, rng: StdRng) -> SolveResult {
+        let now = Instant::now();
+        let mut search = Search::new(self, method, rng);
+        let status = self.next_solution(&mut search);
+
+        SolveResult {
+            solution: self.board.clone(),
+            status,
+            backtrack_counter: search.backtrack_counter(),
+            backjump_counter: search.backjump_counter(),
+            elapsed: now.elapsed(),
+        }
+    }
 
-            // take the next value in the empty slots.
-            match self.domains[self.empty_slots[i]].iter_h(heuristic).next() {
-                Some(solution) => {
-                    self.set_number(row, col, solution);
-                    if self.try_update_domains(heuristic).is_err() {
-                        backtrack_counter += 1;
-                        let previous_version = versions.pop().unwrap();
-                        match self.backtrack(previous_version, solution) {
-                            Ok(_) => {}
-                            Err(_) => match versions.pop() {
-                                Some((previous_board, previous_domains, previous_i)) => match self
-                                    .backtrack(
-                                        (previous_board, previous_domains, previous_i),
-                                        self.board[self.empty_slots[previous_i]],
-                                    ) {
-                                    Ok(prev_i) => {
-                                        i = prev_i;
-                                    }
-                                    Err(prev_i) => {
-                                        i = prev_i;
-                                    }
-                                },
-                                None => {
-                                    println!("No Solution");
-                                    return (UNSOLVED_CHARACTER, backtrack_counter);
-                                }
-                            },
-                        }
-                    } else {
-                        i += 1;
-                    }
-                }
-                None => {
-                    backtrack_counter += 1;
-                    versions.pop();
-                    match versions.pop() {
-                        Some((previous_board, previous_domains, previous_i)) => {
-                            match self.backtrack(
-                                (previous_board, previous_domains, previous_i),
-                                self.board[self.empty_slots[previous_i]],
-                            ) {
-                                Ok(prev_i) => {
-                                    i = prev_i;
-                                }
-                                Err(prev_i) => i = prev_i,
-                            }
-                        }
-                        None => {
-                            println!("No path back");
-                            println!("No Solution");
-                            return (UNSOLVED_CHARACTER, backtrack_counter);
-                        }
-                    }
-                }
-            }
+    /// Counts the solutions of the board, stopping the search once `limit` of them are found.
+    ///
+    /// The board is left untouched, its domains have to be applied already.
+    pub fn count_solutions(&self, limit: usize, method: &Method, rng: StdRng) -> SolutionCount {
+        let found = self.solutions_with(method, rng).take(limit).count();
+        if found < limit {
+            SolutionCount::Exactly(found)
+        } else {
+            SolutionCount::AtLeast(found)
         }
+    }
 
-        // if self.solved() {
-        //     println!("SOLVED");
-        //     println!("{}", backtrack_counter);
-        //     self.print_board();
-        //     println!("\n{}", self);
-        // } else {
-        //     println!("NOT SOLVED");
-        //     println!("{}", backtrack_counter);
-        //     self.print_board();
-        //     println!("\n{}", self);
-        // }
+    /// Returns a lazy iterator over all solutions of the board, searching with forward checking.
+    ///
+    /// The board is left untouched, its domains have to be applied already.
+    pub fn solutions(&self) -> Solutions {
+        let method = Method::ForwardChecking {
+            value_heuristic: Heuristic::Greedy,
+            slot_heuristic: Heuristic::Greedy,
+        };
+        self.solutions_with(&method, StdRng::from_entropy())
+    }
 
-        (SOLVED_CHARACTER, backtrack_counter)
+    /// Returns a lazy iterator over all solutions of the board, searching with the given method.
+    pub fn solutions_with(&self, method: &Method, rng: StdRng) -> Solutions {
+        Solutions::new(self.clone(), method, rng)
     }
 
-    fn try_update_domains(&mut self, heuristic: &Heuristic) -> Result<(), ()> {
-        (0..self.board.len())
-            .map(|idx| self.try_update_domain(idx))
-            .collect::<Result<(), ()>>()?;
+    /// Forward checking after a value was placed at `idx`, fails when a domain is left empty.
+    ///
+    /// Only the peers of the cell can change, the value is taken out of their domains and the
+    /// first of them left with a single value gets it, updating its own peers in turn.
+    fn try_update_domains(&mut self, idx: usize, trail: &mut Trail) -> Result<(), ()> {
+        self.try_update_peers(idx, trail)?;
 
-        // if there was some value changed, try to update domains once more, if not,
+        // if there was some value changed, update the peers of that cell too, if not,
         // there's no need to check it
-        if (0..self.board.len())
-            .filter_map(|idx| self.try_update_value(idx, heuristic))
-            .next()
-            .is_some()
+        if let Some(single) = self.board_peers[idx]
+            .iter()
+            .copied()
+            .find(|&peer| self.board[peer] == 0 && self.domains[peer].len() == 1)
         {
-            (0..self.board.len())
-                .map(|idx| self.try_update_domain(idx))
-                .collect::<Result<(), ()>>()?;
-            // self.try_update_domains()?;
+            let value = self.domains[single].current();
+            self.set_value_trailed(trail, single, value);
+            self.try_update_peers(single, trail)?;
         }
 
         Ok(())
     }
 
-    fn try_update_domain(&mut self, idx: usize) -> Result<(), ()> {
-        let (row, col) = self.board_coords[idx];
-        let square = Self::square(row, col);
-        let mut domain = self.domains[idx];
+    fn try_update_peers(&mut self, idx: usize, trail: &mut Trail) -> Result<(), ()> {
+        let value = self.board[idx];
+        for n in 0..self.board_peers[idx].len() {
+            let peer = self.board_peers[idx][n];
+            let mut domain = self.domains[peer];
+            domain.remove(value);
 
-        self.update_domain(&mut domain, idx, self.board_rows[row].iter())?;
-        self.update_domain(&mut domain, idx, self.board_cols[col].iter())?;
-        self.update_domain(&mut domain, idx, self.board_squares[square].iter())?;
-
-        self.domains[idx] = domain;
+            // if no value can be placed, return error, since it is not a good solution
+            if domain.is_empty() {
+                return Err(());
+            }
+            self.set_domain_trailed(trail, peer, domain);
+        }
 
         Ok(())
     }
 
-    fn update_domain<'a, I>(&self, domain: &mut Domain, index: usize, iter: I) -> Result<(), ()>
-    where
-        I: Iterator<Item = &'a usize>,
-    {
-        iter.for_each(|&idx| {
-            if index != idx {
-                domain.remove(self.board[idx]);
+    /// Makes the domains of the whole board arc consistent, failing when one of them is left
+    /// empty.
+    ///
+    /// Cells left with a single value are filled in.
+    pub(crate) fn make_arc_consistent(&mut self) -> Result<(), ()> {
+        let arcs = (0..self.board.len())
+            .flat_map(|idx| self.peers(idx).iter().map(move |&peer| (idx, peer)))
+            .collect();
+        self.ac3(arcs, &mut Trail::default())
+    }
+
+    /// Restores arc consistency after the domain of the cell at `idx` changed.
+    pub(crate) fn propagate_arcs(&mut self, idx: usize, trail: &mut Trail) -> Result<(), ()> {
+        let mut domain = Domain::new();
+        domain.insert(self.board[idx]);
+        self.set_domain_trailed(trail, idx, domain);
+
+        let arcs = self.peers(idx).iter().map(|&peer| (peer, idx)).collect();
+        self.ac3(arcs, trail)
+    }
+
+    // AC-3 over the constraints between every two peers, which have to hold different values,
+    // each arc is a cell together with the peer whose domain it has to agree with
+    fn ac3(&mut self, mut queue: VecDeque<(usize, usize)>, trail: &mut Trail) -> Result<(), ()> {
+        while let Some((idx, peer)) = queue.pop_front() {
+            if !self.revise(idx, peer, trail) {
+                continue;
             }
-        });
 
-        // if no value can be placed, return error, since it is not a good solution
-        if domain.is_empty() {
-            Err(())
-        } else {
-            Ok(())
+            let domain = self.domains[idx];
+            if domain.is_empty() {
+                return Err(());
+            }
+            if domain.len() == 1 {
+                self.set_value_trailed(trail, idx, domain.current());
+            }
+
+            // the peers of the cell have to be checked again against its smaller domain
+            queue.extend(
+                self.board_peers[idx]
+                    .iter()
+                    .filter(|&&other| other != peer)
+                    .map(|&other| (other, idx)),
+            );
         }
+
+        Ok(())
     }
 
-    fn try_update_value(&mut self, idx: usize, heuristic: &Heuristic) -> Option<()> {
-        let value = HeuristicDomainOperations::next(&self.domains[idx], heuristic);
-        if self.domains[idx].len() == 1 && value != self.board[idx] {
-            self.board[idx] = value;
-            Some(())
-        } else {
-            None
+    // removes the values of the cell which leave no value to the peer, returns whether the
+    // domain changed; a value is only unsupported if it is the last one of the peer
+    fn revise(&mut self, idx: usize, peer: usize, trail: &mut Trail) -> bool {
+        let peer_domain = self.domains[peer];
+        if peer_domain.len() != 1 {
+            return false;
         }
+
+        let value = peer_domain.current();
+        let mut domain = self.domains[idx];
+        domain.remove(value);
+        if domain == self.domains[idx] {
+            return false;
+        }
+        self.set_domain_trailed(trail, idx, domain);
+        true
     }
 
     pub fn apply_domain(&mut self, domain: Domain) -> &mut Self {
@@ -388,7 +694,7 @@ impl Sudoku {
             // println!("removing {} from domain\nnow it is: {:?}", val, &domain);
         }
 
-        let square = Self::square(row, col);
+        let square = self.square(row, col);
         for &val in self
             .get_values(&self.board_squares[square])
             .iter()
@@ -411,78 +717,24 @@ impl FromIterator<(usize, u32)> for Sudoku {
     where
         T: IntoIterator<Item = (usize, u32)>,
     {
-        let mut empty_slots: Vec<usize> = Vec::new();
         // 0 means, that the cell is empty
-        let board: Vec<u32> = iter
-            .into_iter()
-            .map(|(idx, val)| {
-                if val == 0 {
-                    empty_slots.push(idx);
-                }
-                val
-            })
-            .collect();
-
-        // the domain size is a square root of the length of the board
-        let domain_size = (board.len() as f32).sqrt().floor() as usize;
+        let mut cells: Vec<(usize, u32)> = iter.into_iter().collect();
+        cells.sort_unstable_by_key(|&(idx, _)| idx);
+        let board: Vec<u32> = cells.into_iter().map(|(_, val)| val).collect();
 
-        let board_coords: Vec<(usize, usize)> = {
-            fn coord(index: usize) -> (usize, usize) {
-                let row = index / 9;
-                let col = index % 9;
-                (row, col)
-            }
-            (0..board.len()).map(coord).collect()
-        };
+        // the board side is a square root of the length of the board
+        let size = (board.len() as f32).sqrt().floor() as usize;
+        let (box_rows, box_cols) = box_dimensions(size);
 
-        // what indices are in each col?
-        let board_rows: Vec<Vec<usize>> = (0..domain_size)
-            .map(|x| {
-                (0..domain_size)
-                    .map(|y| Self::index(x, y))
-                    .collect::<Vec<usize>>()
-            })
-            .collect();
-        // what indices are in each col?
-        let board_cols: Vec<Vec<usize>> = (0..domain_size)
-            .map(|x| {
-                (0..domain_size)
-                    .map(|y| Self::index(y, x))
-                    .collect::<Vec<usize>>()
-            })
-            .collect();
-
-        let board_squares = {
-            let mut map = vec![vec![0usize; 9]; 9];
-            for k in 0..9 {
-                for i in 0..9 {
-                    let index = Self::index(k, i);
-                    let block = Self::square(k, i);
-                    let index_in_block = Self::index_in_block(k, i);
-                    map[block][index_in_block] = index;
-                }
-            }
-            (0..domain_size).for_each(|x| {
-                (0..domain_size).for_each(|y| {
-                    map[Self::square(x, y)][Self::index_in_block(x, y)] = Self::index(x, y);
-                })
-            });
-            map
-        };
+        Self::with_box(box_rows, box_cols, board)
+    }
+}
 
-        let domains = board.iter().map(|_| Domain::new()).collect();
+impl FromStr for Sudoku {
+    type Err = ParseError;
 
-        Self {
-            board,
-            domains,
-            empty_slots,
-            board_rows,
-            board_cols,
-            board_coords,
-            board_squares,
-            domain_size,
-            domain: Domain::new(),
-        }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from_flattened(s)
     }
 }
 
@@ -491,7 +743,223 @@ impl fmt::Display for Sudoku {
         write!(
             f,
             "{}",
-            self.board.iter().map(|v| v.to_string()).collect::<String>()
+            self.board
+                .iter()
+                .map(|&v| match v {
+                    0 => '0',
+                    v => value_to_char(v),
+                })
+                .collect::<String>()
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{self, Format};
+
+    const PUZZLE: &str =
+        "..2.3...8.....8....31.2.....6..5.27..1.....5.2.4.6..31....8.6.5.......13..531.4..";
+
+    // full grid with the given boxes, every row shifted by the width of a box and every band by
+    // one more
+    fn full_grid(box_rows: usize, box_cols: usize) -> Sudoku {
+        let size = box_rows * box_cols;
+        let values = (0..size * size)
+            .map(|idx| {
+                let (row, col) = (idx / size, idx % size);
+                ((box_cols * (row % box_rows) + row / box_rows + col) % size) as u32 + 1
+            })
+            .collect();
+        Sudoku::with_box(box_rows, box_cols, values)
+    }
+
+    #[test]
+    fn parses_a_valid_puzzle() {
+        let sudoku = Sudoku::try_from_flattened(PUZZLE).unwrap();
+        assert_eq!(sudoku.domain_size, 9);
+        assert_eq!((sudoku.box_rows, sudoku.box_cols), (3, 3));
+        assert_eq!(sudoku.board[2], 2);
+        assert_eq!(sudoku.board[0], 0);
+    }
+
+    #[test]
+    fn rejects_an_invalid_length() {
+        assert_eq!(
+            Sudoku::try_from_flattened(&PUZZLE[1..]).unwrap_err(),
+            ParseError::InvalidLength {
+                found: 80,
+                expected: 81,
+            }
+        );
+        assert_eq!(
+            Sudoku::try_from_flattened(&PUZZLE[1..])
+                .unwrap_err()
+                .to_string(),
+            "invalid puzzle length 80, expected 81 cells"
+        );
+    }
+
+    #[test]
+    fn rejects_an_empty_puzzle() {
+        for puzzle in &["", "  \n"] {
+            let error = Sudoku::try_from_flattened(puzzle).unwrap_err();
+            assert_eq!(error, ParseError::Empty);
+            assert_eq!(
+                error.to_string(),
+                "empty puzzle, expected the cells of the board"
+            );
+        }
+        assert_eq!(
+            Sudoku::try_with_box(2, 3, "").unwrap_err(),
+            ParseError::Empty
+        );
+    }
+
+    #[test]
+    fn rejects_an_invalid_character() {
+        let puzzle = PUZZLE.replacen('.', "#", 1);
+        assert_eq!(
+            Sudoku::try_from_flattened(&puzzle).unwrap_err(),
+            ParseError::InvalidCharacter {
+                position: 0,
+                character: '#',
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_a_value_out_of_range() {
+        let puzzle = PUZZLE.replacen('.', "a", 1);
+        assert_eq!(
+            Sudoku::try_from_flattened(&puzzle).unwrap_err(),
+            ParseError::ValueOutOfRange {
+                position: 0,
+                character: 'a',
+                value: 10,
+                max: 9,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_conflicting_givens() {
+        // a second 2 in the first row, the first square and the first column
+        let puzzle = PUZZLE.replacen('.', "2", 1);
+        match Sudoku::try_from_flattened(&puzzle).unwrap_err() {
+            ParseError::ConflictingGivens(conflicts) => {
+                assert!(conflicts.iter().all(|conflict| conflict.value == 2));
+                assert!(conflicts.iter().any(|conflict| {
+                    conflict.unit == Unit::Row(0) && conflict.cells == vec![0, 2]
+                }));
+                assert!(conflicts.iter().any(|conflict| {
+                    conflict.unit == Unit::Square(0) && conflict.cells == vec![0, 2]
+                }));
+            }
+            e => panic!("expected conflicting givens, got {}", e),
+        }
+    }
+
+    #[test]
+    fn unchecked_parsing_keeps_conflicting_givens() {
+        let puzzle = PUZZLE.replacen('.', "2", 1);
+        let sudoku = Sudoku::try_from_flattened_unchecked(&puzzle).unwrap();
+        assert_eq!(sudoku.board[0], 2);
+        assert!(!sudoku.conflicts().is_empty());
+    }
+
+    #[test]
+    fn round_trips_the_largest_board() {
+        let (box_rows, box_cols) = box_dimensions(MAX_SIZE);
+        let mut sudoku = full_grid(box_rows, box_cols);
+        assert!(sudoku.conflicts().is_empty());
+        sudoku.board[0] = 0;
+
+        let line = sudoku.to_string();
+        assert!(line.contains('Z'));
+        let read = Sudoku::try_from_flattened(&line).unwrap();
+        assert_eq!((read.box_rows, read.box_cols), (box_rows, box_cols));
+        assert_eq!(read.board, sudoku.board);
+
+        let read = Sudoku::try_from_flattened(&format::write(&sudoku, Format::Line)).unwrap();
+        assert_eq!(read.board, sudoku.board);
+    }
+
+    #[test]
+    fn rejects_a_board_above_the_largest_side() {
+        let side = MAX_SIZE + 1;
+        let puzzle = ".".repeat(side * side);
+        assert!(matches!(
+            Sudoku::try_from_flattened(&puzzle),
+            Err(ParseError::InvalidLength { .. })
+        ));
+    }
+
+    #[test]
+    fn infers_boxes_from_the_side() {
+        assert_eq!(box_dimensions(4), (2, 2));
+        assert_eq!(box_dimensions(6), (2, 3));
+        assert_eq!(box_dimensions(8), (2, 4));
+        assert_eq!(box_dimensions(9), (3, 3));
+        assert_eq!(box_dimensions(12), (3, 4));
+        assert_eq!(box_dimensions(16), (4, 4));
+        assert_eq!(box_dimensions(7), (1, 7));
+    }
+
+    #[test]
+    fn numbers_the_squares_of_non_square_boxes() {
+        let sudoku = Sudoku::with_box(2, 3, Vec::new());
+        assert_eq!(sudoku.square(0, 0), 0);
+        assert_eq!(sudoku.square(1, 5), 1);
+        assert_eq!(sudoku.square(2, 0), 2);
+        assert_eq!(sudoku.square(5, 5), 5);
+        assert_eq!(sudoku.index_in_block(1, 4), 4);
+        assert_eq!(sudoku.index_in_block(3, 2), 5);
+        assert_eq!(sudoku.board_squares[1], vec![3, 4, 5, 9, 10, 11]);
+        assert_eq!(
+            sudoku.board_peers[0],
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 12, 18, 24, 30]
+        );
+    }
+
+    #[test]
+    fn numbers_the_squares_of_larger_boards() {
+        let sudoku = Sudoku::with_box(2, 2, Vec::new());
+        assert_eq!(sudoku.square(3, 3), 3);
+        assert_eq!(sudoku.index_in_block(3, 2), 2);
+        assert_eq!(sudoku.board_squares[3], vec![10, 11, 14, 15]);
+        assert_eq!(sudoku.board_peers[5], vec![0, 1, 4, 6, 7, 9, 13]);
+
+        let sudoku = Sudoku::with_box(4, 4, Vec::new());
+        assert_eq!(sudoku.square(5, 9), 6);
+        assert_eq!(sudoku.square(15, 15), 15);
+        assert_eq!(sudoku.index_in_block(5, 9), 5);
+        assert_eq!(sudoku.board_squares[6][..5], [72, 73, 74, 75, 88]);
+    }
+
+    #[test]
+    fn every_cell_has_the_peers_of_its_units() {
+        for &(box_rows, box_cols) in &[(2, 3), (2, 2), (4, 4), (3, 4)] {
+            let sudoku = Sudoku::with_box(box_rows, box_cols, Vec::new());
+            let size = box_rows * box_cols;
+
+            for (square, cells) in sudoku.board_squares.iter().enumerate() {
+                for (n, &idx) in cells.iter().enumerate() {
+                    let (row, col) = sudoku.board_coords[idx];
+                    assert_eq!(sudoku.square(row, col), square);
+                    assert_eq!(sudoku.index_in_block(row, col), n);
+                }
+            }
+
+            // a row and a column, with the rest of the square on top
+            let peers = 2 * (size - 1) + (box_rows - 1) * (box_cols - 1);
+            for idx in 0..size * size {
+                assert_eq!(sudoku.board_peers[idx].len(), peers);
+                for &peer in &sudoku.board_peers[idx] {
+                    assert!(sudoku.board_peers[peer].contains(&idx));
+                }
+            }
+        }
+    }
+}
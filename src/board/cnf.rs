@@ -0,0 +1,73 @@
+use super::Sudoku;
+use crate::sat::Cnf;
+
+impl Sudoku {
+    /// Boolean variable standing for the value in the cell, numbered from 1 as in DIMACS.
+    pub fn variable(&self, idx: usize, value: u32) -> i32 {
+        (idx * self.domain_size) as i32 + value as i32
+    }
+
+    /// Encodes the puzzle as CNF, with a variable for every value of every cell.
+    ///
+    /// Every cell holds exactly one value, every unit holds every value exactly once and the
+    /// givens are unit clauses. The domains of the board are not part of the formula.
+    pub fn to_cnf(&self) -> Cnf {
+        let size = self.domain_size;
+        let mut cnf = Cnf::new(self.board.len() * size);
+        cnf.comments = vec![
+            format!(
+                "sudoku {}x{} with {}x{} boxes",
+                size, size, self.box_rows, self.box_cols
+            ),
+            format!(
+                "variable (row * {} + column) * {} + value places the value, rows and columns from 0",
+                size, size
+            ),
+        ];
+
+        let values = 1..=size as u32;
+        for idx in 0..self.board.len() {
+            let literals = values.clone().map(|value| self.variable(idx, value));
+            exactly_one(&mut cnf, &literals.collect::<Vec<_>>());
+        }
+        for (_, cells) in self.units() {
+            for value in values.clone() {
+                let literals = cells.iter().map(|&idx| self.variable(idx, value));
+                exactly_one(&mut cnf, &literals.collect::<Vec<_>>());
+            }
+        }
+        for (idx, &value) in self.board.iter().enumerate() {
+            if value != 0 {
+                cnf.add_clause(vec![self.variable(idx, value)]);
+            }
+        }
+
+        cnf
+    }
+
+    /// Clause ruling out the values the empty cells of the board hold now.
+    pub(crate) fn blocking_clause(&self, puzzle: &[u32]) -> Vec<i32> {
+        (0..self.board.len())
+            .filter(|&idx| puzzle[idx] == 0)
+            .map(|idx| -self.variable(idx, self.board[idx]))
+            .collect()
+    }
+
+    /// Fills the board with the values set in the model of `to_cnf`.
+    pub(crate) fn apply_model(&mut self, model: &[bool]) {
+        let size = self.domain_size;
+        for (variable, _) in model.iter().enumerate().filter(|(_, &set)| set) {
+            self.board[variable / size] = (variable % size) as u32 + 1;
+        }
+    }
+}
+
+// one clause for at least one of the literals and one for every pair, for at most one
+fn exactly_one(cnf: &mut Cnf, literals: &[i32]) {
+    cnf.add_clause(literals.to_vec());
+    for (n, &first) in literals.iter().enumerate() {
+        for &second in &literals[n + 1..] {
+            cnf.add_clause(vec![-first, -second]);
+        }
+    }
+}
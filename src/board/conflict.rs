@@ -0,0 +1,38 @@
+use std::fmt;
+
+/// A row, column or square of the board, identified by its number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Unit {
+    Row(usize),
+    Column(usize),
+    Square(usize),
+}
+
+/// The same value placed more than once in a single unit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub unit: Unit,
+    pub value: u32,
+    /// Indices of all cells in the unit holding the value
+    pub cells: Vec<usize>,
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unit::Row(n) => write!(f, "row {}", n),
+            Unit::Column(n) => write!(f, "column {}", n),
+            Unit::Square(n) => write!(f, "square {}", n),
+        }
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "value {} repeated in {} at cells {:?}",
+            self.value, self.unit, self.cells
+        )
+    }
+}
@@ -1,125 +1,216 @@
+use super::Sudoku;
 use crate::bitset::BitSet;
 use crate::heuristic::Heuristic;
 
-use std::slice::Iter;
+use std::cmp;
 
-use rand::prelude::*;
+use rand::rngs::StdRng;
+use rand::Rng;
 
+/// Sparse set of the empty slots, the ones still to take are kept in front of `len`, the taken
+/// ones behind it in the order they were taken, so that both taking and giving back a slot is a
+/// single swap.
 pub struct EmptySlots {
     slots: Vec<usize>,
-    /// A vector working as a stack on which are pushed the taken slots
-    taken_slots: Vec<(usize, usize)>,
+    /// Number of slots still to take
+    len: usize,
+    /// A vector working as a stack on which are pushed the positions the slots were taken from
+    taken_slots: Vec<usize>,
     heuristic: Heuristic,
+    /// A slot which had no possible value from the start
+    dead_end: Option<usize>,
+    /// Cells sharing a row, column or square with every cell of the board
+    peers: Vec<Vec<usize>>,
+    /// Row, column and square of every cell, as indices into `units` and `weights`
+    cell_units: Vec<[usize; 3]>,
+    /// Cells of the rows, then the columns, then the squares
+    units: Vec<Vec<usize>>,
+    /// How many times a cell of the unit could not be assigned, used by dom/wdeg
+    weights: Vec<u64>,
 }
 
 impl EmptySlots {
-    pub fn set_heuristic(&mut self, heuristic: &Heuristic) {
-        self.heuristic = heuristic.clone();
+    pub fn new(sudoku: &Sudoku, heuristic: &Heuristic) -> Self {
+        let size = sudoku.domain_size;
+        let cell_units = sudoku
+            .board_coords
+            .iter()
+            .map(|&(row, col)| [row, size + col, 2 * size + sudoku.square(row, col)])
+            .collect();
+        let units: Vec<Vec<usize>> = sudoku
+            .board_rows
+            .iter()
+            .chain(sudoku.board_cols.iter())
+            .chain(sudoku.board_squares.iter())
+            .cloned()
+            .collect();
+
+        // slots are taken from the back, so all heuristics but reverse keep them reversed to go
+        // through them in the board order
+        let mut slots = sudoku.empty_slots.clone();
+        if !matches!(heuristic, Heuristic::Reverse) {
+            slots.reverse();
+        }
+
+        Self {
+            len: slots.len(),
+            taken_slots: Vec::with_capacity(slots.len()),
+            dead_end: sudoku
+                .empty_slots
+                .iter()
+                .copied()
+                .find(|&slot| sudoku.domains[slot].is_empty()),
+            slots,
+            heuristic: heuristic.clone(),
+            peers: sudoku.board_peers.clone(),
+            cell_units,
+            weights: vec![1; units.len()],
+            units,
+        }
     }
 
-    pub fn next(&mut self, domains: &[BitSet]) -> Option<usize> {
-        use Heuristic::*;
+    /// Takes the next slot to assign, `Ok(None)` when all of them are taken.
+    ///
+    /// A slot left without any possible value can never be assigned, so instead of taking one,
+    /// it is returned as `Err`, telling the search to change an earlier assignment.
+    pub fn next(
+        &mut self,
+        board: &[u32],
+        domains: &[BitSet],
+        rng: &mut StdRng,
+    ) -> Result<Option<usize>, usize> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        if let Some(dead_end) = self.dead_end {
+            return Err(dead_end);
+        }
 
-        if self.slots.is_empty() {
-            return None;
+        let pos = self.pick(board, domains, rng);
+        let slot = self.slots[pos];
+        if domains[slot].is_empty() {
+            return Err(slot);
         }
 
+        self.take(pos);
+        Ok(Some(slot))
+    }
+
+    // position of the slot chosen by the heuristic out of the remaining ones
+    fn pick(&self, board: &[u32], domains: &[BitSet], rng: &mut StdRng) -> usize {
+        use Heuristic::*;
+
         // dbg!(&domains);
 
-        let slot = match self.heuristic {
-            Greedy => {
-                let slot = self.slots.remove(0);
-                self.taken_slots.push((0, slot));
-                slot
-            }
-            Reverse => {
-                let len = self.slots.len();
-                let slot = self.slots.remove(len - 1);
-                self.taken_slots.push((len - 1, slot));
-                slot
-            }
-            Random => {
-                let mut rand = thread_rng();
-                let pos = rand.gen_range(0, self.slots.len());
-                let slot = self.slots.remove(pos);
-                self.taken_slots.push((pos, slot));
-                slot
-            }
-            MostConstrainedVariable => {
-                // take the position with the least possible domain values
-                let mut vec = self
-                    .slots
-                    .iter()
-                    .enumerate()
-                    .map(|(idx, &slot)| (idx, domains[slot].len()))
-                    .filter(|&(_, len)| len != 0)
-                    .collect::<Vec<_>>();
-                vec.sort_unstable_by(|&(_, a), (_, b)| a.cmp(b));
-                let (idx, _) = *vec.first()?;
-                let slot = self.slots.remove(idx);
-                self.taken_slots.push((idx, slot));
-
-                // dbg!(slot);
-                // dbg!(&self.taken_slots);
-                // dbg!(domains[slot]);
-                slot
+        match self.heuristic {
+            // the order of the slots already is the one to take them in, value heuristics also
+            // take the slots in order
+            Greedy | Reverse | LeastConstrainingValue => self.len - 1,
+            Random => rng.gen_range(0, self.len),
+            // take the position with the least possible domain values
+            MostConstrainedVariable => self.best_by_key(|slot| domains[slot].len()),
+            // take the position with the most possible domain values
+            LeastConstrainedVariable => self.best_by_key(|slot| cmp::Reverse(domains[slot].len())),
+            // take the position with the most empty peers
+            Degree => self.best_by_key(|slot| cmp::Reverse(self.degree(board, slot))),
+            // least possible domain values, ties go to the most empty peers
+            MostConstrainedVariableDegree => self
+                .best_by_key(|slot| (domains[slot].len(), cmp::Reverse(self.degree(board, slot)))),
+            DomainOverWeightedDegree => {
+                // smallest ratio between the domain size and the weights of the units still
+                // holding other empty cells, compared by cross multiplication
+                let (pos, _, _) = (0..self.len)
+                    .rev()
+                    .map(|pos| {
+                        let slot = self.slots[pos];
+                        (pos, domains[slot].len() as u64, self.wdeg(board, slot))
+                    })
+                    .fold(
+                        None,
+                        |best: Option<(usize, u64, u64)>, current| match best {
+                            Some((_, dom, wdeg)) if dom * current.2 <= current.1 * wdeg => best,
+                            _ => Some(current),
+                        },
+                    )
+                    .unwrap();
+                pos
             }
-            LeastConstrainedVariable => {
-                // take the position with the most possible domain values
-                let mut vec = self
-                    .slots
-                    .iter()
-                    .map(|&slot| domains[slot].len())
-                    .enumerate()
-                    .collect::<Vec<_>>();
-                vec.sort_unstable_by(|(_, a), &(_, b)| b.cmp(a));
-                let (idx, _) = *vec.first()?;
-                let slot = self.slots.remove(idx);
-                self.taken_slots.push((idx, slot));
-
-                // dbg!(domains[slot]);
-                slot
-            }
-        };
-        // dbg!(slot);
+        }
+    }
 
-        Some(slot)
+    // position of the remaining slot with the smallest key, the first one in the order of the
+    // slots among equal ones
+    fn best_by_key<K, F>(&self, key: F) -> usize
+    where
+        K: Ord,
+        F: Fn(usize) -> K,
+    {
+        (0..self.len)
+            .rev()
+            .min_by_key(|&pos| key(self.slots[pos]))
+            .unwrap()
     }
 
+    /// Records that the slot could not be assigned, raising the weights of its units.
+    pub fn fail(&mut self, slot: usize) {
+        for &unit in &self.cell_units[slot] {
+            self.weights[unit] += 1;
+        }
+    }
+
+    // moves the slot at the position right behind the remaining ones
+    fn take(&mut self, pos: usize) {
+        self.len -= 1;
+        self.slots.swap(pos, self.len);
+        self.taken_slots.push(pos);
+    }
+
+    // number of empty peers of the slot
+    fn degree(&self, board: &[u32], slot: usize) -> usize {
+        self.peers[slot]
+            .iter()
+            .filter(|&&peer| board[peer] == 0)
+            .count()
+    }
+
+    // summed weights of the units of the slot in which another cell is empty
+    fn wdeg(&self, board: &[u32], slot: usize) -> u64 {
+        self.cell_units[slot]
+            .iter()
+            .filter(|&&unit| {
+                self.units[unit]
+                    .iter()
+                    .any(|&cell| cell != slot && board[cell] == 0)
+            })
+            .map(|&unit| self.weights[unit])
+            .sum()
+    }
+
+    /// Gives back the current slot and returns the previous one, which stays taken, so that it
+    /// can be assigned again.
     pub fn backtrack(&mut self) -> Option<usize> {
         // dbg!(&self.taken_slots);
-        if self.taken_slots.is_empty() {
-            return None;
-        }
+        let pos = self.taken_slots.pop()?;
 
-        // remove the current one from the stack
-        let (idx, slot) = self.taken_slots.pop().unwrap();
-        self.slots.insert(idx, slot);
+        // the current one is right behind the remaining slots, swap it back to its position
+        self.slots.swap(pos, self.len);
+        self.len += 1;
 
         // go back to the previous value
-        let (idx, slot) = self.taken_slots.pop()?;
-        self.slots.insert(idx, slot);
-
-        Some(slot)
+        self.current()
     }
-}
 
-impl From<Vec<usize>> for EmptySlots {
-    fn from(vec: Vec<usize>) -> Self {
-        Self {
-            taken_slots: Vec::with_capacity(vec.len()),
-            slots: vec,
-            heuristic: Heuristic::Greedy,
-        }
+    /// Number of slots taken so far.
+    pub fn taken(&self) -> usize {
+        self.taken_slots.len()
     }
-}
 
-impl From<Iter<'_, usize>> for EmptySlots {
-    fn from(iter: Iter<usize>) -> Self {
-        Self {
-            taken_slots: Vec::with_capacity(iter.len()),
-            slots: iter.copied().collect(),
-            heuristic: Heuristic::Greedy,
+    /// Returns the most recently taken slot.
+    pub fn current(&self) -> Option<usize> {
+        if self.taken_slots.is_empty() {
+            None
+        } else {
+            Some(self.slots[self.len])
         }
     }
 }
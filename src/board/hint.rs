@@ -0,0 +1,101 @@
+use std::fmt;
+
+use super::{Step, Sudoku, Technique};
+
+/// The simplest deduction available on a board, together with the cells justifying it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hint {
+    pub step: Step,
+    /// Filled cells ruling out the other options of a single, the pattern of the step speaks for
+    /// itself for the other techniques
+    pub reasons: Vec<usize>,
+}
+
+impl Hint {
+    pub fn technique(&self) -> Technique {
+        self.step.technique
+    }
+
+    /// Cell filled in by the hint together with its value, if it is a single.
+    pub fn placement(&self) -> Option<(usize, u32)> {
+        self.step.placements.first().copied()
+    }
+}
+
+impl Sudoku {
+    /// Finds the next hint for a partially filled board, without solving it.
+    ///
+    /// The candidates are the applied domains, without the values of the filled peers, so the
+    /// cells can be filled in between the hints. A board without applied domains gets all the
+    /// values allowed by its peers.
+    pub fn hint(&self) -> Option<Hint> {
+        let mut board = self.clone();
+        if board.domain.is_empty() {
+            board.apply_domain(board.full_domain());
+        } else {
+            for idx in 0..board.board.len() {
+                if board.board[idx] == 0 {
+                    board.domains[idx] = board.domains[idx].intersection(board.find_domain(idx));
+                }
+            }
+        }
+
+        let step = board.next_step()?;
+        let reasons = board.reasons(&step);
+        Some(Hint { step, reasons })
+    }
+
+    // filled cells excluding the other options of a single
+    fn reasons(&self, step: &Step) -> Vec<usize> {
+        let (idx, value) = match step.placements[..] {
+            [placement] => placement,
+            _ => return Vec::new(),
+        };
+
+        let mut reasons: Vec<usize> = match (step.technique, step.unit) {
+            // every other value is already in one of the peers
+            (Technique::NakedSingle, _) => {
+                let peers = self.peers(idx);
+                self.full_domain()
+                    .iter()
+                    .filter(|&other| other != value)
+                    .filter_map(|other| {
+                        peers
+                            .iter()
+                            .copied()
+                            .find(|&peer| self.board[peer] == other)
+                    })
+                    .collect()
+            }
+            // every other empty cell of the unit sees the value
+            (Technique::HiddenSingle, Some(unit)) => self
+                .units()
+                .find(|&(other, _)| other == unit)
+                .map(|(_, cells)| cells.clone())
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|&cell| cell != idx && self.board[cell] == 0)
+                .filter_map(|cell| {
+                    self.peers(cell)
+                        .iter()
+                        .copied()
+                        .find(|&peer| self.board[peer] == value)
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        reasons.sort_unstable();
+        reasons.dedup();
+        reasons
+    }
+}
+
+impl fmt::Display for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.step)?;
+        if !self.reasons.is_empty() {
+            write!(f, ", because of cells {:?}", self.reasons)?;
+        }
+        Ok(())
+    }
+}
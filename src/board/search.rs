@@ -0,0 +1,553 @@
+use rand::rngs::StdRng;
+
+use super::empty_slots::EmptySlots;
+use super::trail::Trail;
+use super::{Domain, Sudoku};
+use crate::dlx::Matrix;
+use crate::heuristic::Heuristic;
+use crate::sat::Solver;
+use crate::solver::{Method, Status};
+
+/// Mark of the trail before visiting the given slot.
+type Version = (usize, usize);
+
+/// State of a depth first search, kept between the solutions, so that the search can be resumed
+/// after one is found.
+pub(crate) enum Search {
+    Backtracking(BacktrackingSearch),
+    ForwardChecking(ForwardCheckingSearch),
+    /// Forward checking search propagating with AC-3 instead
+    ArcConsistency(ForwardCheckingSearch),
+    DancingLinks(DancingLinksSearch),
+    Sat(SatSearch),
+}
+
+pub(crate) struct BacktrackingSearch {
+    slots: EmptySlots,
+    /// Domains of the cells before the search
+    domain_holder: Vec<Domain>,
+    value_heuristic: Heuristic,
+    rng: StdRng,
+    /// Earlier slots blamed for the values ruled out in every slot, by cell
+    conflict_sets: Vec<Vec<usize>>,
+    /// Order in which the slots holding a value were taken, by cell
+    depths: Vec<Option<usize>>,
+    backtrack_counter: u64,
+    /// Number of times the search went back over more than one slot at once
+    backjump_counter: u64,
+    solved: bool,
+}
+
+pub(crate) struct ForwardCheckingSearch {
+    slots: EmptySlots,
+    versions: Vec<Version>,
+    /// Changes made to the board since the start of the search
+    trail: Trail,
+    value_heuristic: Heuristic,
+    rng: StdRng,
+    backtrack_counter: u64,
+    solved: bool,
+    /// Whether the search took any step yet
+    started: bool,
+}
+
+/// Exact cover encoding of the board, with a row for every value an empty cell can take.
+pub(crate) struct DancingLinksSearch {
+    matrix: Matrix,
+    /// False when the givens already cover a column twice
+    consistent: bool,
+}
+
+/// CNF encoding of the board, every solution found is blocked before looking for the next one.
+pub(crate) struct SatSearch {
+    solver: Solver,
+    /// The board before the search, telling which cells the solutions fill
+    puzzle: Vec<u32>,
+    solved: bool,
+}
+
+/// Lazy iterator over the solutions of a board, see `Sudoku::solutions`.
+///
+/// Every call to `next` resumes the search where the previous solution was found, so taking only
+/// a few solutions costs only as much as finding them.
+pub struct Solutions {
+    board: Sudoku,
+    search: Search,
+    exhausted: bool,
+}
+
+impl Solutions {
+    pub(crate) fn new(board: Sudoku, method: &Method, rng: StdRng) -> Self {
+        let search = Search::new(&board, method, rng);
+        Self {
+            board,
+            search,
+            exhausted: false,
+        }
+    }
+
+    /// Number of backtracks done by the search so far.
+    pub fn backtrack_counter(&self) -> u64 {
+        self.search.backtrack_counter()
+    }
+
+    /// Number of backjumps done by the search so far, only backtracking jumps.
+    pub fn backjump_counter(&self) -> u64 {
+        self.search.backjump_counter()
+    }
+}
+
+impl Iterator for Solutions {
+    type Item = Sudoku;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.exhausted {
+            return None;
+        }
+
+        match self.board.next_solution(&mut self.search) {
+            Status::Solved => Some(self.board.clone()),
+            Status::Unsolved => {
+                self.exhausted = true;
+                None
+            }
+        }
+    }
+}
+
+impl Search {
+    pub(crate) fn new(sudoku: &Sudoku, method: &Method, rng: StdRng) -> Self {
+        match method {
+            Method::Backtracking {
+                value_heuristic,
+                slot_heuristic,
+            } => {
+                let slots = EmptySlots::new(sudoku, slot_heuristic);
+                Search::Backtracking(BacktrackingSearch {
+                    slots,
+                    domain_holder: sudoku.domains.clone(),
+                    value_heuristic: value_heuristic.clone(),
+                    rng,
+                    conflict_sets: vec![Vec::new(); sudoku.board.len()],
+                    depths: vec![None; sudoku.board.len()],
+                    backtrack_counter: 0,
+                    backjump_counter: 0,
+                    solved: false,
+                })
+            }
+            Method::ForwardChecking {
+                value_heuristic,
+                slot_heuristic,
+            } => Search::ForwardChecking(ForwardCheckingSearch::new(
+                sudoku,
+                value_heuristic,
+                slot_heuristic,
+                rng,
+            )),
+            Method::ArcConsistency {
+                value_heuristic,
+                slot_heuristic,
+            } => Search::ArcConsistency(ForwardCheckingSearch::new(
+                sudoku,
+                value_heuristic,
+                slot_heuristic,
+                rng,
+            )),
+            Method::DancingLinks => Search::DancingLinks(DancingLinksSearch::new(sudoku)),
+            Method::Sat => Search::Sat(SatSearch::new(sudoku)),
+        }
+    }
+
+    pub(crate) fn backtrack_counter(&self) -> u64 {
+        match self {
+            Search::Backtracking(search) => search.backtrack_counter,
+            Search::ForwardChecking(search) | Search::ArcConsistency(search) => {
+                search.backtrack_counter
+            }
+            Search::DancingLinks(search) => search.matrix.backtracks(),
+            Search::Sat(search) => search.solver.conflicts(),
+        }
+    }
+
+    pub(crate) fn backjump_counter(&self) -> u64 {
+        match self {
+            Search::Backtracking(search) => search.backjump_counter,
+            _ => 0,
+        }
+    }
+}
+
+impl ForwardCheckingSearch {
+    fn new(
+        sudoku: &Sudoku,
+        value_heuristic: &Heuristic,
+        slot_heuristic: &Heuristic,
+        rng: StdRng,
+    ) -> Self {
+        Self {
+            slots: EmptySlots::new(sudoku, slot_heuristic),
+            versions: Vec::new(),
+            trail: Trail::default(),
+            value_heuristic: value_heuristic.clone(),
+            rng,
+            backtrack_counter: 0,
+            solved: false,
+            started: false,
+        }
+    }
+}
+
+impl DancingLinksSearch {
+    // every cell has to hold a value and every unit has to hold every value once, so there is a
+    // column for each cell and one for each value of each unit, the row of a value in a cell
+    // covers the cell and the value in the units of the cell
+    fn new(sudoku: &Sudoku) -> Self {
+        let size = sudoku.domain_size;
+        let mut cell_units = vec![Vec::new(); sudoku.board.len()];
+        let mut units = 0;
+        for (_, cells) in sudoku.units() {
+            for &idx in cells {
+                cell_units[idx].push(units);
+            }
+            units += 1;
+        }
+
+        let cells = sudoku.board.len();
+        let mut matrix = Matrix::new(cells + units * size);
+        let mut givens = Vec::new();
+        for (idx, unit_list) in cell_units.iter().enumerate() {
+            let values = match sudoku.board[idx] {
+                0 => sudoku.domains[idx].iter().collect(),
+                value => {
+                    givens.push(idx * size + value as usize - 1);
+                    vec![value]
+                }
+            };
+            for value in values {
+                let mut columns = vec![idx];
+                columns.extend(
+                    unit_list
+                        .iter()
+                        .map(|unit| cells + unit * size + value as usize - 1),
+                );
+                matrix.add_row(idx * size + value as usize - 1, &columns);
+            }
+        }
+
+        let consistent = givens.into_iter().all(|row| matrix.select(row));
+        Self { matrix, consistent }
+    }
+}
+
+impl SatSearch {
+    // the values missing from the domains of the empty cells are ruled out on top of the puzzle
+    fn new(sudoku: &Sudoku) -> Self {
+        let mut solver = Solver::new(&sudoku.to_cnf());
+        for idx in (0..sudoku.board.len()).filter(|&idx| sudoku.board[idx] == 0) {
+            for value in
+                (1..=sudoku.domain_size as u32).filter(|&v| !sudoku.domains[idx].contains(v))
+            {
+                solver.add_clause(&[-sudoku.variable(idx, value)]);
+            }
+        }
+
+        Self {
+            solver,
+            puzzle: sudoku.board.clone(),
+            solved: false,
+        }
+    }
+}
+
+impl Sudoku {
+    /// Runs the search until the next solution is found or there are no more of them.
+    ///
+    /// After a solution, the next call continues with the value following the last decision.
+    pub(crate) fn next_solution(&mut self, search: &mut Search) -> Status {
+        match search {
+            Search::Backtracking(search) => self.search(search),
+            Search::ForwardChecking(search) => self.search_fc(search, Sudoku::try_update_domains),
+            Search::ArcConsistency(search) => {
+                if !search.started && self.make_arc_consistent().is_err() {
+                    return Status::Unsolved;
+                }
+                self.search_fc(search, Sudoku::propagate_arcs)
+            }
+            Search::DancingLinks(search) => self.search_dlx(search),
+            Search::Sat(search) => self.search_sat(search),
+        }
+    }
+
+    /// Backtracking search with conflict-directed backjumping, a slot left without values goes
+    /// back to the latest slot which ruled out one of them instead of the previous one.
+    fn search(&mut self, search: &mut BacktrackingSearch) -> Status {
+        // slot which has to be assigned again, instead of taking the next one
+        let mut retry = None;
+        if search.solved {
+            search.solved = false;
+            match search.slots.current() {
+                Some(slot) => {
+                    // nothing ruled out the value of the solution, so any earlier slot may have
+                    // to change once the slot runs out of values
+                    let depths = &search.depths;
+                    let earlier = (0..self.board.len())
+                        .filter(|&cell| cell != slot && depths[cell].is_some());
+                    search.conflict_sets[slot].extend(earlier);
+                    retry = Some(slot);
+                }
+                None => return Status::Unsolved,
+            }
+        }
+
+        loop {
+            let slot = match retry.take() {
+                Some(slot) => slot,
+                None => match search
+                    .slots
+                    .next(&self.board, &self.domains, &mut search.rng)
+                {
+                    Ok(Some(slot)) => {
+                        search.depths[slot] = Some(search.slots.taken() - 1);
+                        slot
+                    }
+                    Ok(None) => break,
+                    Err(dead_end) => {
+                        // the cell has no value whatever the other ones hold
+                        search.backtrack_counter += 1;
+                        search.slots.fail(dead_end);
+                        self.give_back_all(search);
+                        return Status::Unsolved;
+                    }
+                },
+            };
+
+            let (row, col) = self.board_coords[slot];
+            match self.find_solution(row, col, &search.value_heuristic, &mut search.rng) {
+                Some(solution) => {
+                    self.set_number(row, col, solution);
+                }
+                None => {
+                    // no solution found, go back to the latest slot to blame
+                    search.backtrack_counter += 1;
+                    search.slots.fail(slot);
+                    match self.backjump(search, slot) {
+                        Some(culprit) => retry = Some(culprit),
+                        None => return Status::Unsolved,
+                    }
+                }
+            }
+        }
+
+        search.solved = true;
+        Status::Solved
+    }
+
+    // blames every value of the slot ruled out by the board on the earliest slot holding it,
+    // then gives back the slots down to the latest one in the conflict set, which inherits the
+    // rest of the set
+    fn backjump(&mut self, search: &mut BacktrackingSearch, slot: usize) -> Option<usize> {
+        for value in search.domain_holder[slot].iter() {
+            let culprit = self.board_peers[slot]
+                .iter()
+                .filter(|&&peer| self.board[peer] == value)
+                .filter_map(|&peer| search.depths[peer].map(|depth| (depth, peer)))
+                .min();
+            if let Some((_, peer)) = culprit {
+                if !search.conflict_sets[slot].contains(&peer) {
+                    search.conflict_sets[slot].push(peer);
+                }
+            }
+        }
+
+        let conflicts = std::mem::take(&mut search.conflict_sets[slot]);
+        let culprit = match conflicts
+            .iter()
+            .copied()
+            .max_by_key(|&cell| search.depths[cell])
+        {
+            Some(culprit) => culprit,
+            None => {
+                self.give_back_all(search);
+                return None;
+            }
+        };
+
+        let mut current = slot;
+        let mut jumped = 0;
+        while current != culprit {
+            self.give_back(search, current);
+            current = search
+                .slots
+                .backtrack()
+                .expect("the culprit is still taken");
+            jumped += 1;
+        }
+        if jumped > 1 {
+            search.backjump_counter += 1;
+        }
+
+        for cell in conflicts {
+            if cell != culprit && !search.conflict_sets[culprit].contains(&cell) {
+                search.conflict_sets[culprit].push(cell);
+            }
+        }
+        Some(culprit)
+    }
+
+    // empties the slot, restoring its domain from before the search
+    fn give_back(&mut self, search: &mut BacktrackingSearch, slot: usize) {
+        self.board[slot] = 0;
+        self.domains[slot] = search.domain_holder[slot];
+        search.conflict_sets[slot].clear();
+        search.depths[slot] = None;
+    }
+
+    // empties every taken slot, once the search knows there is no solution
+    fn give_back_all(&mut self, search: &mut BacktrackingSearch) {
+        while let Some(slot) = search.slots.current() {
+            self.give_back(search, slot);
+            search.slots.backtrack();
+        }
+    }
+
+    /// Forward checking search, `propagate` updates the domains after a value is placed in the
+    /// given slot and fails when one of them is left empty.
+    fn search_fc<P>(&mut self, search: &mut ForwardCheckingSearch, propagate: P) -> Status
+    where
+        P: Fn(&mut Sudoku, usize, &mut Trail) -> Result<(), ()>,
+    {
+        search.started = true;
+        // slot which has to be assigned again, instead of taking the next one
+        let mut retry = None;
+        if search.solved {
+            search.solved = false;
+            match self.step_back(search) {
+                Some(slot) => retry = Some(slot),
+                None => return Status::Unsolved,
+            }
+        }
+
+        loop {
+            let slot = match retry.take() {
+                Some(slot) => slot,
+                None => match search
+                    .slots
+                    .next(&self.board, &self.domains, &mut search.rng)
+                {
+                    Ok(Some(slot)) => slot,
+                    Ok(None) => break,
+                    Err(dead_end) => {
+                        // a cell has no value left, so the last assignment has to change
+                        search.backtrack_counter += 1;
+                        search.slots.fail(dead_end);
+                        match self.step_back(search) {
+                            Some(slot) => retry = Some(slot),
+                            None => return Status::Unsolved,
+                        }
+                        continue;
+                    }
+                },
+            };
+
+            search.versions.push((search.trail.mark(), slot));
+            // if value of cell in the slot is not 0, then it was previously set during forward
+            // checking.
+            if self.board[slot] != 0 {
+                continue;
+            }
+
+            // take the next value of the slot.
+            if let Some(solution) = self.next_value(
+                slot,
+                self.domains[slot],
+                &search.value_heuristic,
+                &mut search.rng,
+            ) {
+                self.set_value_trailed(&mut search.trail, slot, solution);
+                if propagate(self, slot, &mut search.trail).is_ok() {
+                    continue;
+                }
+            }
+            search.backtrack_counter += 1;
+            search.slots.fail(slot);
+
+            match self.step_back(search) {
+                Some(slot) => retry = Some(slot),
+                None => return Status::Unsolved,
+            }
+        }
+
+        search.solved = true;
+        Status::Solved
+    }
+
+    fn search_dlx(&mut self, search: &mut DancingLinksSearch) -> Status {
+        if !search.consistent {
+            return Status::Unsolved;
+        }
+
+        match search.matrix.next_solution() {
+            Some(rows) => {
+                // every row is the value of a cell, as in `DancingLinksSearch::new`
+                for row in rows {
+                    self.board[row / self.domain_size] = (row % self.domain_size) as u32 + 1;
+                }
+                Status::Solved
+            }
+            None => Status::Unsolved,
+        }
+    }
+
+    fn search_sat(&mut self, search: &mut SatSearch) -> Status {
+        if search.solved {
+            search.solved = false;
+            search
+                .solver
+                .add_clause(&self.blocking_clause(&search.puzzle));
+        }
+
+        match search.solver.solve() {
+            Some(model) => {
+                self.apply_model(&model);
+                search.solved = true;
+                Status::Solved
+            }
+            None => Status::Unsolved,
+        }
+    }
+
+    // restores the last version, removing the value taken there from the domain of its slot,
+    // slots left without any value are given back and the search goes further back
+    fn step_back(&mut self, search: &mut ForwardCheckingSearch) -> Option<usize> {
+        loop {
+            let version = search.versions.pop()?;
+            let value = self.board[version.1];
+            match self.backtrack(version, value, &mut search.trail) {
+                Ok(slot) => return Some(slot),
+                Err(_) => {
+                    search.slots.backtrack();
+                }
+            }
+        }
+    }
+
+    // undoes the changes made since the version, the removal of the value stays on the trail to
+    // be undone together with the previous version
+    fn backtrack(
+        &mut self,
+        (mark, slot): Version,
+        value: u32,
+        trail: &mut Trail,
+    ) -> Result<usize, usize> {
+        self.undo(trail, mark);
+        self.set_value_trailed(trail, slot, 0);
+        let mut domain = self.domains[slot];
+        domain.remove(value);
+        self.set_domain_trailed(trail, slot, domain);
+        if domain.is_empty() {
+            return Err(slot);
+        }
+
+        Ok(slot)
+    }
+}
@@ -0,0 +1,727 @@
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::{Domain, Sudoku, Unit};
+
+/// Deduction a human solver would use to fill a cell or remove candidates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Technique {
+    /// The cell has a single candidate left
+    NakedSingle,
+    /// The value has a single place left in a unit
+    HiddenSingle,
+    NakedPair,
+    NakedTriple,
+    NakedQuad,
+    HiddenPair,
+    HiddenTriple,
+    HiddenQuad,
+    /// The candidates of a value in a square all lie in one row or column
+    PointingPair,
+    /// The candidates of a value in a row or column all lie in one square
+    BoxLineReduction,
+    XWing,
+    Swordfish,
+    XYWing,
+    SimpleColoring,
+}
+
+/// A single deduction, with the pattern it was found in and the changes it makes to the board.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub technique: Technique,
+    /// Unit holding the whole pattern, if there is a single one
+    pub unit: Option<Unit>,
+    /// Values the pattern is made of
+    pub values: Vec<u32>,
+    /// Cells forming the pattern
+    pub cells: Vec<usize>,
+    /// Cells filled in, together with their values
+    pub placements: Vec<(usize, u32)>,
+    /// Candidates removed, as the cell together with the value taken out of its domain
+    pub eliminations: Vec<(usize, u32)>,
+}
+
+impl Technique {
+    /// Every technique, from the simplest to the hardest one, in the order they are tried.
+    pub const ALL: [Technique; 14] = [
+        Technique::HiddenSingle,
+        Technique::NakedSingle,
+        Technique::PointingPair,
+        Technique::BoxLineReduction,
+        Technique::NakedPair,
+        Technique::HiddenPair,
+        Technique::NakedTriple,
+        Technique::HiddenTriple,
+        Technique::XWing,
+        Technique::NakedQuad,
+        Technique::HiddenQuad,
+        Technique::Swordfish,
+        Technique::XYWing,
+        Technique::SimpleColoring,
+    ];
+}
+
+impl Sudoku {
+    /// Finds the simplest deduction that can be made on the board, if there is any.
+    ///
+    /// The domains of the empty cells are taken as their candidates, so they have to be applied
+    /// already.
+    pub fn next_step(&self) -> Option<Step> {
+        Technique::ALL
+            .iter()
+            .find_map(|&technique| self.find_step(technique))
+    }
+
+    /// Looks for a deduction made with the given technique.
+    pub fn find_step(&self, technique: Technique) -> Option<Step> {
+        use Technique::*;
+
+        match technique {
+            NakedSingle => self.naked_single(),
+            HiddenSingle => self.hidden_single(),
+            NakedPair => self.naked_subset(2),
+            NakedTriple => self.naked_subset(3),
+            NakedQuad => self.naked_subset(4),
+            HiddenPair => self.hidden_subset(2),
+            HiddenTriple => self.hidden_subset(3),
+            HiddenQuad => self.hidden_subset(4),
+            PointingPair => self.pointing(),
+            BoxLineReduction => self.box_line_reduction(),
+            XWing => self.fish(2),
+            Swordfish => self.fish(3),
+            XYWing => self.xy_wing(),
+            SimpleColoring => self.simple_coloring(),
+        }
+    }
+
+    /// Fills in the cells and removes the candidates of the step.
+    pub fn apply_step(&mut self, step: &Step) {
+        for &(idx, value) in &step.placements {
+            self.board[idx] = value;
+            let mut domain = Domain::new();
+            domain.insert(value);
+            self.domains[idx] = domain;
+            for &peer in &self.board_peers[idx] {
+                if self.board[peer] == 0 {
+                    self.domains[peer].remove(value);
+                }
+            }
+        }
+
+        for &(idx, value) in &step.eliminations {
+            self.domains[idx].remove(value);
+        }
+    }
+
+    /// Solves the board with the techniques alone, as far as they get, returning the steps taken.
+    ///
+    /// Whether the board got solved is left to `is_solved`.
+    pub fn solve_logically(&mut self) -> Vec<Step> {
+        let mut steps = Vec::new();
+        while let Some(step) = self.next_step() {
+            self.apply_step(&step);
+            steps.push(step);
+        }
+        steps
+    }
+
+    // candidates of an empty cell, none for a filled one
+    fn candidates(&self, idx: usize) -> Domain {
+        if self.board[idx] == 0 {
+            self.domains[idx]
+        } else {
+            Domain::new()
+        }
+    }
+
+    // empty cells of the unit still holding the value as a candidate
+    fn places(&self, cells: &[usize], value: u32) -> Vec<usize> {
+        cells
+            .iter()
+            .copied()
+            .filter(|&idx| self.candidates(idx).contains(value))
+            .collect()
+    }
+
+    fn sees(&self, a: usize, b: usize) -> bool {
+        let (row_a, col_a) = self.board_coords[a];
+        let (row_b, col_b) = self.board_coords[b];
+        a != b
+            && (row_a == row_b
+                || col_a == col_b
+                || self.square(row_a, col_a) == self.square(row_b, col_b))
+    }
+
+    fn naked_single(&self) -> Option<Step> {
+        (0..self.board.len())
+            .find(|&idx| self.candidates(idx).len() == 1)
+            .map(|idx| {
+                let value = self.domains[idx].current();
+                Step::placement(Technique::NakedSingle, None, idx, value)
+            })
+    }
+
+    fn hidden_single(&self) -> Option<Step> {
+        for (unit, cells) in self.units() {
+            for value in self.full_domain().iter() {
+                if let [idx] = self.places(cells, value)[..] {
+                    return Some(Step::placement(
+                        Technique::HiddenSingle,
+                        Some(unit),
+                        idx,
+                        value,
+                    ));
+                }
+            }
+        }
+        None
+    }
+
+    // `size` cells of a unit holding only `size` candidates between them, which then cannot go
+    // anywhere else in the unit
+    fn naked_subset(&self, size: usize) -> Option<Step> {
+        for (unit, cells) in self.units() {
+            let open = cells
+                .iter()
+                .copied()
+                .filter(|&idx| (2..=size).contains(&self.candidates(idx).len()))
+                .collect::<Vec<_>>();
+
+            for subset in combinations(&open, size) {
+                let values = subset
+                    .iter()
+                    .fold(Domain::new(), |all, &idx| all.union(self.candidates(idx)));
+                if values.len() != size {
+                    continue;
+                }
+
+                let eliminations = cells
+                    .iter()
+                    .filter(|idx| !subset.contains(idx))
+                    .flat_map(|&idx| {
+                        let candidates = self.candidates(idx);
+                        values
+                            .iter()
+                            .filter(move |&value| candidates.contains(value))
+                            .map(move |value| (idx, value))
+                    })
+                    .collect::<Vec<_>>();
+
+                if !eliminations.is_empty() {
+                    let technique = match size {
+                        2 => Technique::NakedPair,
+                        3 => Technique::NakedTriple,
+                        _ => Technique::NakedQuad,
+                    };
+                    return Some(Step {
+                        technique,
+                        unit: Some(unit),
+                        values: values.iter().collect(),
+                        cells: subset,
+                        placements: Vec::new(),
+                        eliminations,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    // `size` values of a unit which can only go to the same `size` cells, so the cells cannot
+    // hold anything else
+    fn hidden_subset(&self, size: usize) -> Option<Step> {
+        for (unit, cells) in self.units() {
+            let open = self
+                .full_domain()
+                .iter()
+                .filter(|&value| (2..=size).contains(&self.places(cells, value).len()))
+                .collect::<Vec<_>>();
+
+            for subset in combinations(&open, size) {
+                let mut places = subset
+                    .iter()
+                    .flat_map(|&value| self.places(cells, value))
+                    .collect::<Vec<_>>();
+                places.sort_unstable();
+                places.dedup();
+                if places.len() != size {
+                    continue;
+                }
+
+                let eliminations = places
+                    .iter()
+                    .flat_map(|&idx| {
+                        self.candidates(idx)
+                            .iter()
+                            .filter(|value| !subset.contains(value))
+                            .map(move |value| (idx, value))
+                    })
+                    .collect::<Vec<_>>();
+
+                if !eliminations.is_empty() {
+                    let technique = match size {
+                        2 => Technique::HiddenPair,
+                        3 => Technique::HiddenTriple,
+                        _ => Technique::HiddenQuad,
+                    };
+                    return Some(Step {
+                        technique,
+                        unit: Some(unit),
+                        values: subset,
+                        cells: places,
+                        placements: Vec::new(),
+                        eliminations,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    fn pointing(&self) -> Option<Step> {
+        for (square, cells) in self.board_squares.iter().enumerate() {
+            for value in self.full_domain().iter() {
+                let places = self.places(cells, value);
+                if places.len() < 2 {
+                    continue;
+                }
+
+                let (row, col) = self.board_coords[places[0]];
+                let line = if places.iter().all(|&idx| self.board_coords[idx].0 == row) {
+                    &self.board_rows[row]
+                } else if places.iter().all(|&idx| self.board_coords[idx].1 == col) {
+                    &self.board_cols[col]
+                } else {
+                    continue;
+                };
+
+                let eliminations = self
+                    .places(line, value)
+                    .into_iter()
+                    .filter(|idx| !cells.contains(idx))
+                    .map(|idx| (idx, value))
+                    .collect::<Vec<_>>();
+                if !eliminations.is_empty() {
+                    return Some(Step {
+                        technique: Technique::PointingPair,
+                        unit: Some(Unit::Square(square)),
+                        values: vec![value],
+                        cells: places,
+                        placements: Vec::new(),
+                        eliminations,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    fn box_line_reduction(&self) -> Option<Step> {
+        let lines = self
+            .units()
+            .filter(|(unit, _)| !matches!(unit, Unit::Square(_)));
+        for (unit, cells) in lines {
+            for value in self.full_domain().iter() {
+                let places = self.places(cells, value);
+                if places.len() < 2 {
+                    continue;
+                }
+
+                let square_of = |idx: usize| {
+                    let (row, col) = self.board_coords[idx];
+                    self.square(row, col)
+                };
+                let square = square_of(places[0]);
+                if places.iter().any(|&idx| square_of(idx) != square) {
+                    continue;
+                }
+
+                let eliminations = self
+                    .places(&self.board_squares[square], value)
+                    .into_iter()
+                    .filter(|idx| !cells.contains(idx))
+                    .map(|idx| (idx, value))
+                    .collect::<Vec<_>>();
+                if !eliminations.is_empty() {
+                    return Some(Step {
+                        technique: Technique::BoxLineReduction,
+                        unit: Some(unit),
+                        values: vec![value],
+                        cells: places,
+                        placements: Vec::new(),
+                        eliminations,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    // X-Wing for two lines, Swordfish for three: the candidates of a value in `size` rows lie in
+    // the same `size` columns, so the value cannot go anywhere else in those columns, and the
+    // same with rows and columns swapped
+    fn fish(&self, size: usize) -> Option<Step> {
+        let technique = if size == 2 {
+            Technique::XWing
+        } else {
+            Technique::Swordfish
+        };
+
+        for value in self.full_domain().iter() {
+            for &by_rows in [true, false].iter() {
+                let (base, cover) = if by_rows {
+                    (&self.board_rows, &self.board_cols)
+                } else {
+                    (&self.board_cols, &self.board_rows)
+                };
+                let cover_of = |idx: usize| {
+                    let (row, col) = self.board_coords[idx];
+                    if by_rows {
+                        col
+                    } else {
+                        row
+                    }
+                };
+
+                let open = (0..base.len())
+                    .filter(|&line| (2..=size).contains(&self.places(&base[line], value).len()))
+                    .collect::<Vec<_>>();
+
+                for lines in combinations(&open, size) {
+                    let cells = lines
+                        .iter()
+                        .flat_map(|&line| self.places(&base[line], value))
+                        .collect::<Vec<_>>();
+                    let mut crossing = cells.iter().map(|&idx| cover_of(idx)).collect::<Vec<_>>();
+                    crossing.sort_unstable();
+                    crossing.dedup();
+                    if crossing.len() != size {
+                        continue;
+                    }
+
+                    let eliminations = crossing
+                        .iter()
+                        .flat_map(|&line| self.places(&cover[line], value))
+                        .filter(|idx| !cells.contains(idx))
+                        .map(|idx| (idx, value))
+                        .collect::<Vec<_>>();
+                    if !eliminations.is_empty() {
+                        return Some(Step {
+                            technique,
+                            unit: None,
+                            values: vec![value],
+                            cells,
+                            placements: Vec::new(),
+                            eliminations,
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // a pivot with candidates xy seeing two pincers with xz and yz, whichever value the pivot
+    // takes, one of the pincers holds z, so no cell seeing both of them can
+    fn xy_wing(&self) -> Option<Step> {
+        let pairs = (0..self.board.len())
+            .filter(|&idx| self.candidates(idx).len() == 2)
+            .collect::<Vec<_>>();
+
+        for &pivot in &pairs {
+            let pivot_values = self.candidates(pivot);
+            let wings = pairs
+                .iter()
+                .copied()
+                .filter(|&wing| self.sees(pivot, wing))
+                .filter(|&wing| {
+                    let shared = self
+                        .candidates(wing)
+                        .iter()
+                        .filter(|&value| pivot_values.contains(value))
+                        .count();
+                    shared == 1
+                })
+                .collect::<Vec<_>>();
+
+            for (n, &first) in wings.iter().enumerate() {
+                for &second in &wings[n + 1..] {
+                    let (first_values, second_values) =
+                        (self.candidates(first), self.candidates(second));
+                    // the pincers have to share the value the pivot lacks, and none of its own
+                    let shared = first_values
+                        .iter()
+                        .filter(|&value| second_values.contains(value))
+                        .collect::<Vec<_>>();
+                    let value = match shared[..] {
+                        [value] if !pivot_values.contains(value) => value,
+                        _ => continue,
+                    };
+
+                    let eliminations = (0..self.board.len())
+                        .filter(|&idx| idx != pivot && self.sees(idx, first))
+                        .filter(|&idx| self.sees(idx, second))
+                        .filter(|&idx| self.candidates(idx).contains(value))
+                        .map(|idx| (idx, value))
+                        .collect::<Vec<_>>();
+                    if !eliminations.is_empty() {
+                        return Some(Step {
+                            technique: Technique::XYWing,
+                            unit: None,
+                            values: pivot_values
+                                .union(first_values)
+                                .union(second_values)
+                                .iter()
+                                .collect(),
+                            cells: vec![pivot, first, second],
+                            placements: Vec::new(),
+                            eliminations,
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // chains of the units where a value has exactly two places, coloured alternately: one of
+    // the colours holds the value, so a colour seeing itself is wrong, and a cell seeing both
+    // colours cannot hold the value
+    fn simple_coloring(&self) -> Option<Step> {
+        for value in self.full_domain().iter() {
+            let mut links: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+            for (_, cells) in self.units() {
+                if let [a, b] = self.places(cells, value)[..] {
+                    links.entry(a).or_default().push(b);
+                    links.entry(b).or_default().push(a);
+                }
+            }
+
+            let mut colors: BTreeMap<usize, bool> = BTreeMap::new();
+            for &start in links.keys() {
+                if colors.contains_key(&start) {
+                    continue;
+                }
+
+                // colour the whole chain of the cell
+                let mut chain = vec![start];
+                colors.insert(start, true);
+                let mut stack = vec![start];
+                while let Some(idx) = stack.pop() {
+                    let color = colors[&idx];
+                    for &next in &links[&idx] {
+                        if let Entry::Vacant(entry) = colors.entry(next) {
+                            entry.insert(!color);
+                            chain.push(next);
+                            stack.push(next);
+                        }
+                    }
+                }
+                chain.sort_unstable();
+
+                let eliminations = self.color_wrap(&chain, &colors).unwrap_or_else(|| {
+                    (0..self.board.len())
+                        .filter(|idx| !chain.contains(idx))
+                        .filter(|&idx| self.candidates(idx).contains(value))
+                        .filter(|&idx| {
+                            let seen = chain.iter().filter(|&&other| self.sees(idx, other));
+                            let mut seen = seen.map(|other| colors[other]);
+                            let first = seen.next();
+                            first.is_some() && seen.any(|color| Some(color) != first)
+                        })
+                        .collect()
+                });
+
+                if !eliminations.is_empty() {
+                    return Some(Step {
+                        technique: Technique::SimpleColoring,
+                        unit: None,
+                        values: vec![value],
+                        cells: chain,
+                        placements: Vec::new(),
+                        eliminations: eliminations.into_iter().map(|idx| (idx, value)).collect(),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    // cells of the colour appearing twice in a unit of the chain
+    fn color_wrap(&self, chain: &[usize], colors: &BTreeMap<usize, bool>) -> Option<Vec<usize>> {
+        let wrong = chain.iter().enumerate().find_map(|(n, &a)| {
+            chain[n + 1..]
+                .iter()
+                .find(|&&b| colors[&a] == colors[&b] && self.sees(a, b))
+                .map(|_| colors[&a])
+        })?;
+
+        Some(
+            chain
+                .iter()
+                .copied()
+                .filter(|idx| colors[idx] == wrong)
+                .collect(),
+        )
+    }
+}
+
+impl Step {
+    fn placement(technique: Technique, unit: Option<Unit>, idx: usize, value: u32) -> Self {
+        Self {
+            technique,
+            unit,
+            values: vec![value],
+            cells: vec![idx],
+            placements: vec![(idx, value)],
+            eliminations: Vec::new(),
+        }
+    }
+}
+
+// every way to choose `size` of the items, keeping their order
+fn combinations<T: Copy>(items: &[T], size: usize) -> Vec<Vec<T>> {
+    if size == 0 {
+        return vec![Vec::new()];
+    }
+
+    (0..items.len())
+        .flat_map(|n| {
+            combinations(&items[n + 1..], size - 1)
+                .into_iter()
+                .map(move |mut rest| {
+                    rest.insert(0, items[n]);
+                    rest
+                })
+        })
+        .collect()
+}
+
+impl fmt::Display for Technique {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Technique::*;
+
+        let name = match self {
+            NakedSingle => "naked single",
+            HiddenSingle => "hidden single",
+            NakedPair => "naked pair",
+            NakedTriple => "naked triple",
+            NakedQuad => "naked quad",
+            HiddenPair => "hidden pair",
+            HiddenTriple => "hidden triple",
+            HiddenQuad => "hidden quad",
+            PointingPair => "pointing pair",
+            BoxLineReduction => "box/line reduction",
+            XWing => "x-wing",
+            Swordfish => "swordfish",
+            XYWing => "xy-wing",
+            SimpleColoring => "simple coloring",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:?}", self.technique, self.values)?;
+        if let Some(unit) = self.unit {
+            write!(f, " in {}", unit)?;
+        }
+        write!(f, " at cells {:?}", self.cells)?;
+
+        let mut changes = self
+            .placements
+            .iter()
+            .map(|(idx, value)| format!("placed {} at cell {}", value, idx))
+            .collect::<Vec<_>>();
+
+        let mut removed: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+        self.eliminations
+            .iter()
+            .for_each(|&(idx, value)| removed.entry(value).or_default().push(idx));
+        changes.extend(
+            removed
+                .into_iter()
+                .map(|(value, cells)| format!("removed {} from cells {:?}", value, cells)),
+        );
+
+        write!(f, ": {}", changes.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solve, Method, Status};
+
+    // valid, solvable puzzles of `sudoku.csv` with their id and solution, the solution found by
+    // the search where the file has none
+    fn solved_puzzles() -> Vec<(String, Sudoku, Vec<u32>)> {
+        include_str!("../../sudoku.csv")
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split(';').collect();
+                let puzzle = Sudoku::try_from_flattened(fields[2]).ok()?;
+                let mut solution = puzzle.clone();
+                match fields
+                    .get(3)
+                    .and_then(|field| Sudoku::try_from_flattened(field).ok())
+                {
+                    Some(given) => solution = given,
+                    None => {
+                        solution.apply_domain(solution.full_domain());
+                        let result = solve(&mut solution, &Method::DancingLinks);
+                        if result.status != Status::Solved {
+                            return None;
+                        }
+                    }
+                }
+                Some((fields[0].to_string(), puzzle, solution.board))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn no_step_contradicts_the_solution() {
+        let puzzles = solved_puzzles();
+        assert!(puzzles.len() > 30);
+        for (id, mut sudoku, solution) in puzzles {
+            sudoku.apply_domain(sudoku.full_domain());
+            while let Some(step) = sudoku.next_step() {
+                for &(idx, value) in &step.placements {
+                    assert_eq!(value, solution[idx], "puzzle {}: {}", id, step);
+                }
+                for &(idx, value) in &step.eliminations {
+                    assert_ne!(value, solution[idx], "puzzle {}: {}", id, step);
+                }
+                sudoku.apply_step(&step);
+            }
+        }
+    }
+
+    #[test]
+    fn every_technique_keeps_the_solution() {
+        for (id, mut sudoku, solution) in solved_puzzles() {
+            sudoku.apply_domain(sudoku.full_domain());
+            while let Some(step) = sudoku.next_step() {
+                for &technique in Technique::ALL.iter() {
+                    if let Some(step) = sudoku.find_step(technique) {
+                        let keeps_solution = step
+                            .placements
+                            .iter()
+                            .all(|&(idx, value)| value == solution[idx])
+                            && step
+                                .eliminations
+                                .iter()
+                                .all(|&(idx, value)| value != solution[idx]);
+                        assert!(keeps_solution, "puzzle {}: {}", id, step);
+                    }
+                }
+                sudoku.apply_step(&step);
+            }
+        }
+    }
+}
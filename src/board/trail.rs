@@ -0,0 +1,51 @@
+use super::{Domain, Sudoku};
+
+/// Undo log of the changes made to the cells and domains of a board, so that a search can go
+/// back by restoring only what changed instead of keeping copies of the whole board.
+#[derive(Debug, Default)]
+pub(crate) struct Trail {
+    changes: Vec<Change>,
+}
+
+/// A single change, holding what was there before it.
+#[derive(Debug)]
+enum Change {
+    Value(usize, u32),
+    Domain(usize, Domain),
+}
+
+impl Trail {
+    /// Position in the log, changes made after it can be undone with `Sudoku::undo`.
+    pub fn mark(&self) -> usize {
+        self.changes.len()
+    }
+}
+
+impl Sudoku {
+    /// Places the value in the cell, logging the old one.
+    pub(crate) fn set_value_trailed(&mut self, trail: &mut Trail, idx: usize, value: u32) {
+        if self.board[idx] != value {
+            trail.changes.push(Change::Value(idx, self.board[idx]));
+            self.board[idx] = value;
+        }
+    }
+
+    /// Replaces the domain of the cell, logging the old one.
+    pub(crate) fn set_domain_trailed(&mut self, trail: &mut Trail, idx: usize, domain: Domain) {
+        if self.domains[idx] != domain {
+            trail.changes.push(Change::Domain(idx, self.domains[idx]));
+            self.domains[idx] = domain;
+        }
+    }
+
+    /// Reverts every change logged after the mark, newest first.
+    pub(crate) fn undo(&mut self, trail: &mut Trail, mark: usize) {
+        while trail.changes.len() > mark {
+            match trail.changes.pop() {
+                Some(Change::Value(idx, value)) => self.board[idx] = value,
+                Some(Change::Domain(idx, domain)) => self.domains[idx] = domain,
+                None => break,
+            }
+        }
+    }
+}
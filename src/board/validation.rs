@@ -0,0 +1,39 @@
+use std::fmt;
+
+use super::Conflict;
+
+/// Everything that keeps a board from being a correct solution.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validation {
+    /// Values repeated in a row, column or square
+    pub conflicts: Vec<Conflict>,
+    /// Cells without a value
+    pub empty_cells: Vec<usize>,
+    /// Cells whose value differs from the given of the puzzle
+    pub changed_givens: Vec<usize>,
+}
+
+impl Validation {
+    pub fn is_valid(&self) -> bool {
+        self.conflicts.is_empty() && self.empty_cells.is_empty() && self.changed_givens.is_empty()
+    }
+}
+
+impl fmt::Display for Validation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_valid() {
+            return write!(f, "valid");
+        }
+
+        let mut problems = Vec::new();
+        if !self.empty_cells.is_empty() {
+            problems.push(format!("empty cells {:?}", self.empty_cells));
+        }
+        if !self.changed_givens.is_empty() {
+            problems.push(format!("changed givens at cells {:?}", self.changed_givens));
+        }
+        problems.extend(self.conflicts.iter().map(|conflict| conflict.to_string()));
+
+        write!(f, "{}", problems.join("; "))
+    }
+}
@@ -0,0 +1,304 @@
+/// Exact cover problem solved with Knuth's Algorithm X on dancing links.
+///
+/// The search can be resumed after every solution, so the solutions can be taken one by one.
+#[derive(Debug, Clone)]
+pub struct Matrix {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    /// Column header of every node, the headers point at themselves
+    column: Vec<usize>,
+    /// Row of every node, unused for the headers
+    row: Vec<usize>,
+    /// Number of nodes left in every column, by header
+    size: Vec<usize>,
+    /// Nodes of the rows chosen so far, one per level of the search
+    stack: Vec<usize>,
+    /// Rows chosen before the search, with `select`
+    selected: Vec<usize>,
+    /// Number of dead ends the search ran into
+    backtracks: u64,
+    solved: bool,
+}
+
+/// The root node, in front of the column headers.
+const ROOT: usize = 0;
+
+impl Matrix {
+    /// Creates a matrix with the given number of columns, all of which have to be covered.
+    pub fn new(columns: usize) -> Self {
+        let headers = columns + 1;
+        let mut matrix = Self {
+            left: (0..headers).map(|n| (n + headers - 1) % headers).collect(),
+            right: (0..headers).map(|n| (n + 1) % headers).collect(),
+            up: (0..headers).collect(),
+            down: (0..headers).collect(),
+            column: (0..headers).collect(),
+            row: vec![0; headers],
+            size: vec![0; headers],
+            stack: Vec::new(),
+            selected: Vec::new(),
+            backtracks: 0,
+            solved: false,
+        };
+        matrix.size[ROOT] = usize::MAX;
+        matrix
+    }
+
+    /// Adds a row covering the given columns, counted from 0.
+    pub fn add_row(&mut self, row: usize, columns: &[usize]) {
+        let first = self.left.len();
+        for (n, &column) in columns.iter().enumerate() {
+            let header = column + 1;
+            let node = first + n;
+            self.left.push(if n == 0 {
+                first + columns.len() - 1
+            } else {
+                node - 1
+            });
+            self.right.push(if n + 1 == columns.len() {
+                first
+            } else {
+                node + 1
+            });
+            self.up.push(self.up[header]);
+            self.down.push(header);
+            self.column.push(header);
+            self.row.push(row);
+
+            let last = self.up[header];
+            self.down[last] = node;
+            self.up[header] = node;
+            self.size[header] += 1;
+        }
+    }
+
+    /// Puts the row into every solution, as a given, returns false if one of its columns is
+    /// already covered.
+    pub fn select(&mut self, row: usize) -> bool {
+        let node = match (ROOT + 1..self.row.len())
+            .find(|&node| self.column[node] != node && self.row[node] == row)
+        {
+            Some(node) => node,
+            None => return false,
+        };
+
+        let mut j = node;
+        loop {
+            let header = self.column[j];
+            if self.right[self.left[header]] != header {
+                return false;
+            }
+            j = self.right[j];
+            if j == node {
+                break;
+            }
+        }
+
+        loop {
+            self.cover(self.column[j]);
+            j = self.right[j];
+            if j == node {
+                break;
+            }
+        }
+        self.selected.push(row);
+        true
+    }
+
+    /// Number of dead ends the search ran into so far.
+    pub fn backtracks(&self) -> u64 {
+        self.backtracks
+    }
+
+    /// Finds the next solution, as the rows making it up, together with the selected ones.
+    pub fn next_solution(&mut self) -> Option<Vec<usize>> {
+        if self.solved {
+            self.solved = false;
+            if !self.advance() {
+                return None;
+            }
+        }
+
+        loop {
+            if self.right[ROOT] == ROOT {
+                self.solved = true;
+                let mut rows = self.selected.clone();
+                rows.extend(self.stack.iter().map(|&node| self.row[node]));
+                return Some(rows);
+            }
+
+            // the column with the fewest rows left
+            let mut header = self.right[ROOT];
+            let mut j = self.right[header];
+            while j != ROOT {
+                if self.size[j] < self.size[header] {
+                    header = j;
+                }
+                j = self.right[j];
+            }
+
+            if self.size[header] == 0 {
+                self.backtracks += 1;
+                if !self.advance() {
+                    return None;
+                }
+                continue;
+            }
+
+            self.cover(header);
+            let node = self.down[header];
+            self.choose(node);
+        }
+    }
+
+    // puts the row of the node into the solution
+    fn choose(&mut self, node: usize) {
+        self.stack.push(node);
+        let mut j = self.right[node];
+        while j != node {
+            self.cover(self.column[j]);
+            j = self.right[j];
+        }
+    }
+
+    // takes back the last chosen rows until one of them has another row to try in its column,
+    // returns false when there are no more
+    fn advance(&mut self) -> bool {
+        while let Some(node) = self.stack.pop() {
+            let mut j = self.left[node];
+            while j != node {
+                self.uncover(self.column[j]);
+                j = self.left[j];
+            }
+
+            let header = self.column[node];
+            let next = self.down[node];
+            if next != header {
+                self.choose(next);
+                return true;
+            }
+            self.uncover(header);
+        }
+        false
+    }
+
+    fn cover(&mut self, header: usize) {
+        let (left, right) = (self.left[header], self.right[header]);
+        self.right[left] = right;
+        self.left[right] = left;
+
+        let mut i = self.down[header];
+        while i != header {
+            let mut j = self.right[i];
+            while j != i {
+                let (up, down) = (self.up[j], self.down[j]);
+                self.down[up] = down;
+                self.up[down] = up;
+                self.size[self.column[j]] -= 1;
+                j = self.right[j];
+            }
+            i = self.down[i];
+        }
+    }
+
+    fn uncover(&mut self, header: usize) {
+        let mut i = self.up[header];
+        while i != header {
+            let mut j = self.left[i];
+            while j != i {
+                self.size[self.column[j]] += 1;
+                let (up, down) = (self.up[j], self.down[j]);
+                self.down[up] = j;
+                self.up[down] = j;
+                j = self.left[j];
+            }
+            i = self.up[i];
+        }
+
+        let (left, right) = (self.left[header], self.right[header]);
+        self.right[left] = header;
+        self.left[right] = header;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Sudoku;
+    use crate::solver::{self, Method, Status};
+
+    fn matrix(columns: usize, rows: &[&[usize]]) -> Matrix {
+        let mut matrix = Matrix::new(columns);
+        for (row, columns) in rows.iter().enumerate() {
+            matrix.add_row(row, columns);
+        }
+        matrix
+    }
+
+    fn solutions(matrix: &mut Matrix) -> Vec<Vec<usize>> {
+        let mut solutions = Vec::new();
+        while let Some(mut rows) = matrix.next_solution() {
+            rows.sort_unstable();
+            solutions.push(rows);
+        }
+        solutions.sort();
+        solutions
+    }
+
+    #[test]
+    fn finds_a_single_exact_cover() {
+        let mut matrix = matrix(4, &[&[0, 1], &[2], &[3], &[1, 2]]);
+        assert_eq!(solutions(&mut matrix), vec![vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn resumes_after_every_solution() {
+        let mut matrix = matrix(4, &[&[0, 3], &[1, 2], &[0, 1], &[2, 3], &[0, 1, 2, 3]]);
+        assert_eq!(
+            solutions(&mut matrix),
+            vec![vec![0, 1], vec![2, 3], vec![4]]
+        );
+    }
+
+    #[test]
+    fn runs_out_of_covers() {
+        let mut matrix = matrix(3, &[&[0, 1], &[1, 2]]);
+        assert_eq!(matrix.next_solution(), None);
+        assert!(matrix.backtracks() > 0);
+    }
+
+    #[test]
+    fn keeps_the_selected_rows() {
+        let mut matrix = matrix(4, &[&[0, 3], &[1, 2], &[0, 1], &[2, 3], &[0, 1, 2, 3]]);
+        assert!(matrix.select(3));
+        assert_eq!(solutions(&mut matrix), vec![vec![2, 3]]);
+    }
+
+    #[test]
+    fn rejects_conflicting_selections() {
+        let mut matrix = matrix(4, &[&[0, 3], &[1, 2], &[0, 1], &[2, 3], &[0, 1, 2, 3]]);
+        assert!(matrix.select(0));
+        assert!(!matrix.select(2));
+        assert!(!matrix.select(5));
+    }
+
+    #[test]
+    fn conflicting_givens_leave_no_solution() {
+        // the 2 in the first cell is given again two cells further
+        let puzzle =
+            "2.2.3...8.....8....31.2.....6..5.27..1.....5.2.4.6..31....8.6.5.......13..531.4..";
+        let mut sudoku = Sudoku::try_from_flattened_unchecked(puzzle).unwrap();
+        sudoku.apply_domain(sudoku.full_domain());
+
+        let result = solver::solve(&mut sudoku, &Method::DancingLinks);
+        assert_eq!(result.status, Status::Unsolved);
+        assert_eq!(
+            sudoku
+                .solutions_with(&Method::DancingLinks, solver::rng(Some(7)))
+                .count(),
+            0
+        );
+    }
+}
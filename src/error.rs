@@ -0,0 +1,63 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::board::Conflict;
+
+/// Reasons for rejecting a puzzle while parsing it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The puzzle has no cells at all
+    Empty,
+    /// The number of cells does not form a supported board
+    InvalidLength { found: usize, expected: usize },
+    /// The character does not stand for any value nor an empty cell
+    InvalidCharacter { position: usize, character: char },
+    /// The character stands for a value that does not fit the board
+    ValueOutOfRange {
+        position: usize,
+        character: char,
+        value: u32,
+        max: u32,
+    },
+    /// The givens already break the rules of sudoku
+    ConflictingGivens(Vec<Conflict>),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "empty puzzle, expected the cells of the board"),
+            ParseError::InvalidLength { found, expected } => write!(
+                f,
+                "invalid puzzle length {}, expected {} cells",
+                found, expected
+            ),
+            ParseError::InvalidCharacter {
+                position,
+                character,
+            } => write!(f, "invalid character {:?} at {}", character, position),
+            ParseError::ValueOutOfRange {
+                position,
+                character,
+                value,
+                max,
+            } => write!(
+                f,
+                "value {} ({:?}) at {} is out of range 1..={}",
+                value, character, position, max
+            ),
+            ParseError::ConflictingGivens(conflicts) => {
+                write!(f, "conflicting givens: ")?;
+                for (i, conflict) in conflicts.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", conflict)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
@@ -0,0 +1,259 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::board::{value_to_char, Sudoku};
+use crate::error::ParseError;
+
+/// Text formats in which puzzles are commonly distributed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// All cells in a single line, empty cells written as `.` or `0`
+    Line,
+    /// Multi-line grid, optionally with spaces and `|`, `-`, `+` separators
+    Grid,
+    /// Simple Sudoku `.ss` file, rows like `..2|.8.|3..` and `---+---+---` separators
+    SimpleSudoku,
+    /// SadMan `.sdk` file, `#` metadata lines followed by the rows of the puzzle
+    SadMan,
+}
+
+/// Guesses the format of the given text.
+pub fn detect(s: &str) -> Format {
+    let lines = s
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+
+    if lines
+        .iter()
+        .any(|line| line.starts_with('#') || line.starts_with('['))
+    {
+        Format::SadMan
+    } else if lines.len() <= 1 {
+        Format::Line
+    } else if lines
+        .iter()
+        .any(|line| line.contains('|') || line.contains('+'))
+        && lines.iter().all(|line| !line.contains(' '))
+    {
+        Format::SimpleSudoku
+    } else {
+        Format::Grid
+    }
+}
+
+/// Reads a puzzle written in any of the supported formats.
+pub fn read(s: &str) -> Result<Sudoku, ParseError> {
+    read_as(s, detect(s))
+}
+
+/// Reads a puzzle written in the given format.
+pub fn read_as(s: &str, format: Format) -> Result<Sudoku, ParseError> {
+    let cells = match format {
+        Format::Line => s.trim().to_string(),
+        Format::Grid | Format::SimpleSudoku => grid_cells(s.lines()),
+        Format::SadMan => {
+            let has_sections = s.lines().any(|line| line.trim().starts_with('['));
+            let mut in_puzzle = !has_sections;
+            let lines = s.lines().filter(|line| {
+                let line = line.trim();
+                if line.starts_with('[') {
+                    in_puzzle = line.eq_ignore_ascii_case("[puzzle]");
+                    return false;
+                }
+                in_puzzle && !line.starts_with('#')
+            });
+            grid_cells(lines)
+        }
+    };
+
+    Sudoku::try_from_flattened(&cells)
+}
+
+// joins the rows of the grid, dropping whitespace and separators
+fn grid_cells<'a, I>(lines: I) -> String
+where
+    I: Iterator<Item = &'a str>,
+{
+    lines
+        .flat_map(str::chars)
+        .filter(|c| !c.is_whitespace() && !matches!(c, '|' | '-' | '+'))
+        .collect()
+}
+
+/// Writes the puzzle in the given format, empty cells are written as `.`.
+pub fn write(sudoku: &Sudoku, format: Format) -> String {
+    match format {
+        Format::Line => sudoku.board.iter().map(|&v| value_to_char(v)).collect(),
+        Format::Grid => write_rows(sudoku, " ", " | ", "-+-", "-"),
+        Format::SimpleSudoku => write_rows(sudoku, "", "|", "+", "-"),
+        Format::SadMan => {
+            let rows = sudoku
+                .board_rows
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|&idx| value_to_char(sudoku.board[idx]))
+                        .collect()
+                })
+                .collect::<Vec<String>>();
+            format!("[Puzzle]\n{}\n", rows.join("\n"))
+        }
+    }
+}
+
+fn write_rows(
+    sudoku: &Sudoku,
+    cell_separator: &str,
+    box_separator: &str,
+    cross: &str,
+    line: &str,
+) -> String {
+    let box_width = sudoku.box_cols + cell_separator.len() * (sudoku.box_cols - 1);
+    let separator = vec![line.repeat(box_width); sudoku.box_rows].join(cross);
+
+    let mut out = String::new();
+    for (row_id, row) in sudoku.board_rows.iter().enumerate() {
+        if row_id != 0 && row_id % sudoku.box_rows == 0 {
+            out.push_str(&separator);
+            out.push('\n');
+        }
+        let boxes = row
+            .chunks(sudoku.box_cols)
+            .map(|cells| {
+                cells
+                    .iter()
+                    .map(|&idx| value_to_char(sudoku.board[idx]).to_string())
+                    .collect::<Vec<_>>()
+                    .join(cell_separator)
+            })
+            .collect::<Vec<_>>();
+        out.push_str(&boxes.join(box_separator));
+        out.push('\n');
+    }
+
+    out
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "line" => Ok(Format::Line),
+            "grid" => Ok(Format::Grid),
+            "ss" => Ok(Format::SimpleSudoku),
+            "sdk" => Ok(Format::SadMan),
+            _ => Err(format!(
+                "unknown format {}, expected one of: line, grid, ss, sdk",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Format::Line => "line",
+            Format::Grid => "grid",
+            Format::SimpleSudoku => "ss",
+            Format::SadMan => "sdk",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE: &str =
+        "..2.3...8.....8....31.2.....6..5.27..1.....5.2.4.6..31....8.6.5.......13..531.4..";
+
+    fn rows() -> Vec<&'static str> {
+        (0..9).map(|row| &PUZZLE[row * 9..row * 9 + 9]).collect()
+    }
+
+    fn round_trip(format: Format) {
+        let sudoku = Sudoku::try_from_flattened(PUZZLE).unwrap();
+        let text = write(&sudoku, format);
+
+        assert_eq!(detect(&text), format, "{}", text);
+        assert_eq!(read(&text).unwrap().board, sudoku.board);
+    }
+
+    #[test]
+    fn round_trips_a_line() {
+        round_trip(Format::Line);
+    }
+
+    #[test]
+    fn round_trips_a_grid() {
+        round_trip(Format::Grid);
+    }
+
+    #[test]
+    fn round_trips_a_simple_sudoku_file() {
+        round_trip(Format::SimpleSudoku);
+    }
+
+    #[test]
+    fn round_trips_a_sadman_file() {
+        round_trip(Format::SadMan);
+    }
+
+    #[test]
+    fn round_trips_a_grid_of_non_square_boxes() {
+        let sudoku = Sudoku::try_from_flattened("1.....4.....3.....2.....6.....5.....").unwrap();
+        for &format in &[Format::Grid, Format::SimpleSudoku] {
+            let text = write(&sudoku, format);
+            assert_eq!(read_as(&text, format).unwrap().board, sudoku.board);
+        }
+    }
+
+    #[test]
+    fn skips_sadman_metadata() {
+        let text = format!("#Asome author\n#Dsome description\n{}", rows().join("\n"));
+
+        assert_eq!(detect(&text), Format::SadMan);
+        assert_eq!(read(&text).unwrap().board, read(PUZZLE).unwrap().board);
+    }
+
+    #[test]
+    fn reads_only_the_puzzle_section() {
+        // a state full of conflicts fails to parse if it is ever read
+        let state = "1".repeat(81);
+        let text = format!(
+            "[Properties]\nRating=3\n[Puzzle]\n{}\n[State]\n{}\n",
+            PUZZLE, state
+        );
+
+        assert_eq!(read(&text).unwrap().board, read(PUZZLE).unwrap().board);
+    }
+
+    #[test]
+    fn tells_a_grid_from_a_simple_sudoku_file() {
+        // rows of bare cells, without any separator, are a grid
+        assert_eq!(detect(&rows().join("\n")), Format::Grid);
+        // spaces between cells make a grid even with separators
+        let spaced = write(&read(PUZZLE).unwrap(), Format::Grid);
+        assert!(spaced.contains('|'));
+        assert_eq!(detect(&spaced), Format::Grid);
+        assert_eq!(detect(PUZZLE), Format::Line);
+    }
+
+    #[test]
+    fn parses_format_names() {
+        for &format in &[
+            Format::Line,
+            Format::Grid,
+            Format::SimpleSudoku,
+            Format::SadMan,
+        ] {
+            assert_eq!(format.to_string().parse::<Format>(), Ok(format));
+        }
+        assert!("csv".parse::<Format>().is_err());
+    }
+}
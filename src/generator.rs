@@ -0,0 +1,150 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::board::{Sudoku, MAX_SIZE};
+use crate::rating::{self, MAX_UNIQUE_DIFFICULTY};
+use crate::solver::{Method, SolutionCount};
+
+/// How many full grids are tried before settling for the closest puzzle.
+const ATTEMPTS: usize = 16;
+
+/// A puzzle with a unique solution together with that solution.
+#[derive(Debug, Clone)]
+pub struct Generated {
+    pub puzzle: Sudoku,
+    pub solution: Sudoku,
+    pub difficulty: f64,
+}
+
+/// Estimates the difficulty of a puzzle on the 0 to 9 scale of the `difficulty` column of
+/// `sudoku.csv`, see `rating::rate`.
+pub fn difficulty(puzzle: &Sudoku) -> f64 {
+    rating::rate(puzzle).difficulty
+}
+
+/// Generates a puzzle with a unique solution whose difficulty falls in `[difficulty,
+/// difficulty + 1)`, the same seed always gives the same puzzle.
+///
+/// The bands above `MAX_UNIQUE_DIFFICULTY` hold the puzzles without a unique solution, so the
+/// difficulty is capped there. If no such puzzle is found within a few attempts, the one closest
+/// to the band is returned.
+///
+/// # Panics
+///
+/// If the side of the board, `box_rows * box_cols`, is 0 or above `MAX_SIZE`.
+pub fn generate(box_rows: usize, box_cols: usize, difficulty: f64, seed: u64) -> Generated {
+    let size = box_rows * box_cols;
+    assert!(
+        size > 0 && size <= MAX_SIZE,
+        "the side of the board has to be from 1 to {}, got {}",
+        MAX_SIZE,
+        size
+    );
+    let mut rng = StdRng::seed_from_u64(seed);
+    let target = difficulty.floor().clamp(0.0, MAX_UNIQUE_DIFFICULTY);
+
+    let distance = |generated: &Generated| (generated.difficulty - target).abs();
+
+    let mut best = dig(&full_grid(box_rows, box_cols, &mut rng), target, &mut rng);
+    for _ in 1..ATTEMPTS {
+        if distance(&best) == 0.0 {
+            break;
+        }
+        let candidate = dig(&full_grid(box_rows, box_cols, &mut rng), target, &mut rng);
+        if distance(&candidate) < distance(&best) {
+            best = candidate;
+        }
+    }
+
+    best
+}
+
+/// Builds a random full grid by shuffling a valid pattern, keeping it valid.
+fn full_grid(box_rows: usize, box_cols: usize, rng: &mut StdRng) -> Sudoku {
+    let size = box_rows * box_cols;
+
+    // shuffle the bands and the rows inside every band, likewise for stacks and columns
+    let shuffled = |groups: usize, group_size: usize, rng: &mut StdRng| {
+        let mut order: Vec<usize> = (0..groups).collect();
+        order.shuffle(rng);
+        order
+            .into_iter()
+            .flat_map(|group| {
+                let mut inner: Vec<usize> = (0..group_size).collect();
+                inner.shuffle(rng);
+                inner.into_iter().map(move |i| group * group_size + i)
+            })
+            .collect::<Vec<_>>()
+    };
+    let rows = shuffled(box_cols, box_rows, rng);
+    let cols = shuffled(box_rows, box_cols, rng);
+    let mut values: Vec<u32> = (1..=size as u32).collect();
+    values.shuffle(rng);
+
+    // the pattern shifts every row by the width of a box and every band by one more
+    let pattern =
+        |row: usize, col: usize| (box_cols * (row % box_rows) + row / box_rows + col) % size;
+    let board = (0..size * size)
+        .map(|idx| values[pattern(rows[idx / size], cols[idx % size])])
+        .collect();
+
+    with_domains(Sudoku::with_box(box_rows, box_cols, board))
+}
+
+/// Removes givens in random order as long as the solution stays unique, returning the puzzle
+/// with the fewest givens that is still within the difficulty band.
+fn dig(solution: &Sudoku, target: f64, rng: &mut StdRng) -> Generated {
+    let mut cells: Vec<usize> = (0..solution.board.len()).collect();
+    cells.shuffle(rng);
+
+    let mut board = solution.board.clone();
+    let mut current = Generated {
+        puzzle: solution.clone(),
+        solution: solution.clone(),
+        difficulty: 0.0,
+    };
+    let mut best = current.clone();
+
+    for idx in cells {
+        let value = board[idx];
+        board[idx] = 0;
+
+        let puzzle = with_domains(Sudoku::with_box(
+            solution.box_rows,
+            solution.box_cols,
+            board.clone(),
+        ));
+        // dancing links draws no random numbers, so the clone leaves the generation reproducible
+        let count = puzzle.count_solutions(2, &Method::DancingLinks, rng.clone());
+        if count != SolutionCount::Exactly(1) {
+            // the solution is no longer unique, put the given back
+            board[idx] = value;
+            continue;
+        }
+
+        current = Generated {
+            difficulty: difficulty(&puzzle),
+            puzzle,
+            solution: solution.clone(),
+        };
+        if current.difficulty <= target {
+            best = current.clone();
+        }
+        if current.difficulty > target {
+            break;
+        }
+    }
+
+    // overshooting the band is closer than staying far below it
+    if best.difficulty < target && current.difficulty - target < target - best.difficulty {
+        current
+    } else {
+        best
+    }
+}
+
+fn with_domains(mut sudoku: Sudoku) -> Sudoku {
+    sudoku.apply_domain(sudoku.full_domain());
+    sudoku
+}
@@ -1,5 +1,9 @@
 use crate::bitset::BitSet;
 
+use rand::rngs::StdRng;
+use std::fmt;
+use std::str::FromStr;
+
 #[derive(Debug, Clone)]
 pub enum Heuristic {
     Greedy,
@@ -7,23 +11,116 @@ pub enum Heuristic {
     Reverse,
     MostConstrainedVariable,
     LeastConstrainedVariable,
+    /// Cell with the most empty peers
+    Degree,
+    /// Most constrained cell, ties broken by the degree
+    MostConstrainedVariableDegree,
+    /// Cell with the smallest domain over the weights of its units, learned from failures
+    DomainOverWeightedDegree,
+    /// Value removing the fewest options from the domains of the peers of the cell
+    LeastConstrainingValue,
+}
+
+impl FromStr for Heuristic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Heuristic::*;
+
+        match s.to_lowercase().as_str() {
+            "greedy" => Ok(Greedy),
+            "random" => Ok(Random),
+            "reverse" => Ok(Reverse),
+            "mcv" | "most-constrained-variable" => Ok(MostConstrainedVariable),
+            "lcvar" | "least-constrained-variable" => Ok(LeastConstrainedVariable),
+            "degree" => Ok(Degree),
+            "mcv-degree" | "most-constrained-variable-degree" => Ok(MostConstrainedVariableDegree),
+            "dom-wdeg" | "domain-over-weighted-degree" => Ok(DomainOverWeightedDegree),
+            "lcvalue" | "least-constraining-value" => Ok(LeastConstrainingValue),
+            _ => Err(format!(
+                "unknown heuristic {}, expected one of: greedy, random, reverse, mcv, lcvar, \
+                 degree, mcv-degree, dom-wdeg, lcvalue",
+                s
+            )),
+        }
+    }
+}
+
+impl Heuristic {
+    /// Parses a heuristic choosing the next value of a cell, rejecting the ones choosing cells.
+    pub fn parse_value(s: &str) -> Result<Self, String> {
+        let heuristic: Heuristic = s.parse()?;
+        if heuristic.orders_values() {
+            Ok(heuristic)
+        } else {
+            Err(format!(
+                "{} chooses cells, not values, expected one of: greedy, random, reverse, lcvalue",
+                heuristic
+            ))
+        }
+    }
+
+    /// Parses a heuristic choosing the next cell to fill, rejecting the ones choosing values.
+    pub fn parse_variable(s: &str) -> Result<Self, String> {
+        let heuristic: Heuristic = s.parse()?;
+        if heuristic.orders_variables() {
+            Ok(heuristic)
+        } else {
+            Err(format!(
+                "{} chooses values, not cells, expected one of: greedy, random, reverse, mcv, \
+                 lcvar, degree, mcv-degree, dom-wdeg",
+                heuristic
+            ))
+        }
+    }
+
+    /// Whether the heuristic means something as the order of the values of a cell.
+    pub fn orders_values(&self) -> bool {
+        use Heuristic::*;
+
+        matches!(self, Greedy | Random | Reverse | LeastConstrainingValue)
+    }
+
+    /// Whether the heuristic means something as the order of the cells being filled.
+    pub fn orders_variables(&self) -> bool {
+        !matches!(self, Heuristic::LeastConstrainingValue)
+    }
+}
+
+impl fmt::Display for Heuristic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Heuristic::*;
+
+        let name = match self {
+            Greedy => "greedy",
+            Random => "random",
+            Reverse => "reverse",
+            MostConstrainedVariable => "mcv",
+            LeastConstrainedVariable => "lcvar",
+            Degree => "degree",
+            MostConstrainedVariableDegree => "mcv-degree",
+            DomainOverWeightedDegree => "dom-wdeg",
+            LeastConstrainingValue => "lcvalue",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 pub trait HeuristicDomainOperations {
     type Item;
 
-    fn next(&self, heuristic: &Heuristic) -> Self::Item;
+    fn next(&self, heuristic: &Heuristic, rng: &mut StdRng) -> Self::Item;
     fn remove(&mut self, v: u32);
 }
 
 impl HeuristicDomainOperations for BitSet {
     type Item = u32;
 
-    fn next(&self, heuristic: &Heuristic) -> Self::Item {
+    fn next(&self, heuristic: &Heuristic, rng: &mut StdRng) -> Self::Item {
         use Heuristic::*;
 
         match heuristic {
-            Random => self.random(),
+            Random => self.random(rng),
             Reverse => self.last(),
             _ => self.current(),
         }
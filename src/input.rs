@@ -0,0 +1,86 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+
+use sudoku_solver::{ParseError, Sudoku};
+
+use crate::opt::InputOpt;
+
+/// A single line of the puzzle file split into its fields.
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// Number of the line in the file, counted from 0
+    pub line: usize,
+    pub fields: Vec<String>,
+}
+
+impl Record {
+    pub fn field(&self, n: usize) -> &str {
+        self.fields.get(n).map(String::as_str).unwrap_or_default()
+    }
+}
+
+impl InputOpt {
+    /// Reads all records of the input, skipping blank lines and the header line if there is one.
+    pub fn records(&self) -> io::Result<Vec<Record>> {
+        let reader: Box<dyn BufRead> = if self.input == "-" {
+            Box::new(BufReader::new(io::stdin()))
+        } else {
+            Box::new(BufReader::new(File::open(&self.input)?))
+        };
+
+        let mut records = reader
+            .lines()
+            .enumerate()
+            .map(|(line, content)| {
+                let content = content?;
+                let fields = content
+                    .trim_start_matches('\u{feff}')
+                    .split(self.delimiter)
+                    .map(str::to_string)
+                    .collect();
+                Ok(Record { line, fields })
+            })
+            .filter(|record| match record {
+                Ok(record) => record.fields.iter().any(|field| !field.trim().is_empty()),
+                Err(_) => true,
+            })
+            .collect::<io::Result<Vec<Record>>>()?;
+
+        let has_header = match records.first() {
+            _ if self.no_header => false,
+            _ if self.header => true,
+            Some(first) => self.is_header(first),
+            None => false,
+        };
+        if has_header {
+            records.remove(0);
+        }
+
+        Ok(records)
+    }
+
+    /// Returns the record at the given line of the input.
+    pub fn nth(&self, nth: usize) -> io::Result<Record> {
+        self.records()?
+            .into_iter()
+            .find(|record| record.line == nth)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("there is no puzzle at line {} of {}", nth, self.input),
+                )
+            })
+    }
+
+    // a line is a header, if its puzzle field has no board's number of cells, like `puzzle1`, or
+    // holds no digit and no empty cell, like `grid`, anything else is kept so that a broken puzzle
+    // gets reported, apart from a first puzzle of the wrong length, which needs `--no-header`
+    fn is_header(&self, record: &Record) -> bool {
+        let field = record.field(self.column).trim();
+        match Sudoku::try_from_flattened_unchecked(field) {
+            Ok(_) | Err(ParseError::Empty) => false,
+            Err(ParseError::InvalidLength { .. }) => true,
+            Err(_) => !field.chars().any(|c| c.is_ascii_digit() || c == '.'),
+        }
+    }
+}
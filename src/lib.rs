@@ -0,0 +1,18 @@
+pub mod bitset;
+pub mod board;
+pub mod dlx;
+pub mod error;
+pub mod format;
+pub mod generator;
+pub mod heuristic;
+pub mod rating;
+pub mod sat;
+pub mod solver;
+
+pub use bitset::BitSet;
+pub use board::{Hint, Solutions, Step, Sudoku, Technique};
+pub use error::ParseError;
+pub use format::Format;
+pub use heuristic::Heuristic;
+pub use rating::Rating;
+pub use solver::{solve, solve_seeded, Method, SolutionCount, SolveResult, Status};
@@ -1,165 +1,410 @@
-mod bitset;
-mod board;
-mod heuristic;
+mod input;
 mod opt;
+mod rate;
+mod validate;
+mod verify;
 
 use rayon::prelude::*;
 use structopt::StructOpt;
 
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::time::Instant;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::process;
 
-use crate::bitset::BitSet;
-use board::Sudoku;
-use heuristic::Heuristic;
-use opt::Opt;
+use input::Record;
+use opt::{InputOpt, MethodName, Opt, SearchOpt, SolverOpt};
+use sudoku_solver::{
+    board::{box_dimensions, MAX_SIZE},
+    format, generator, solver, Format, Heuristic, Method, ParseError, Status, Sudoku,
+};
+
+type RunResult = Result<(f64, Status, u64, u64, String), ParseError>;
 
 fn main() {
     let opt = Opt::from_args();
-    dbg!(&opt);
-    println!();
-
-    // let domain: std::collections::HashSet<u32> = (1..10).collect();
-    let domain = {
-        let mut domain = BitSet::new();
-        (1..10).for_each(|val| domain.insert(val));
-        domain
-    };
-
-    match opt {
-        Opt::Load { nth } => {
-            let f = File::open("sudoku.csv").unwrap();
-            let reader = BufReader::new(f);
-            let line = reader.lines().nth(nth as usize).unwrap().unwrap();
-            let flat_line = line.split(';').nth(2).unwrap();
 
-            let mut board = Sudoku::from_flattened(flat_line);
-            board.print_board(100);
-            board.apply_domain(domain);
-        }
-        Opt::Solve { nth } => {
-            let f = File::open("sudoku.csv").unwrap();
-            let reader = BufReader::new(f);
-            match nth {
-                Some(n) => {
-                    let line = reader.lines().nth(n as usize).unwrap().unwrap();
-                    let (now, result, backtrack_counter, board) = run_solve(line, domain);
-
-                    println!(
-                        "{} | {} | {} | {}| {}\n",
-                        n, now, result, backtrack_counter, board
-                    )
-                }
-                None => {
-                    let results = reader
-                        .lines()
-                        .collect::<Vec<_>>()
-                        .into_par_iter()
-                        .enumerate()
-                        .map(|(idx, line)| {
-                            let line = line.unwrap();
-                            // let line = line.split(';').nth(2).unwrap();
-                            // let mut board = Sudoku::from_flattened(line);
-                            // board.apply_domain(domain);
-                            //
-                            // let now = Instant::now();
-                            // let backtrack_counter = board.solve_fc();
-                            // let now = now.elapsed().as_secs_f64();
-                            // let board = format!("{}", board);
-
-                            let (now, result, backtrack_counter, board) = run_solve(line, domain);
-
-                            (idx, now, result, backtrack_counter, board)
-                        })
-                        .collect::<Vec<(usize, f64, char, u64, String)>>();
-
-                    results
-                        .into_iter()
-                        .for_each(|(idx, now, result, backtrack_counter, board)| {
-                            println!(
-                                "{} | {} | {} | {}| {}\n",
-                                idx, now, result, backtrack_counter, board
-                            )
-                        });
+    let outcome = match opt {
+        Opt::Load { nth, input } => input.nth(nth as usize).map(|record| {
+            match Sudoku::try_from_flattened(record.field(input.column)) {
+                Ok(mut board) => {
+                    board.print_board(100.min(board.board.len() - 1));
+                    board.apply_domain(board.full_domain());
                 }
+                Err(e) => println!("{} | {}", nth, e),
             }
+        }),
+        Opt::Solve { nth, input, search } => select(&search, MethodName::Backtracking)
+            .and_then(|method| run_input(&input, nth, &method, search.solver.seed)),
+        Opt::Verify {
+            nth,
+            input,
+            solution_column,
+            id_column,
+            search,
+        } => {
+            let columns = verify::Columns {
+                id: id_column,
+                puzzle: input.column,
+                solution: solution_column,
+            };
+            select(&search, MethodName::ForwardChecking).and_then(|method| {
+                verify::run(&input, nth, &columns, &method, search.solver.seed).map(|passed| {
+                    if !passed {
+                        process::exit(1);
+                    }
+                })
+            })
         }
-        Opt::SolveFc { nth } => {
-            let f = File::open("sudoku.csv").unwrap();
-            let reader = BufReader::new(f);
-            match nth {
-                Some(n) => {
-                    let line = reader.lines().nth(n as usize).unwrap().unwrap();
-                    let (now, result, backtrack_counter, board) = run_solve_fc(line, domain);
-
-                    println!(
-                        "{} | {} | {} | {}| {}\n",
-                        n, now, result, backtrack_counter, board
-                    )
-                }
-                None => {
-                    let results = reader
-                        .lines()
-                        .collect::<Vec<_>>()
-                        .into_par_iter()
-                        .enumerate()
-                        .map(|(idx, line)| {
-                            let line = line.unwrap();
-                            let line = line.split(';').nth(2).unwrap();
-                            let mut board = Sudoku::from_flattened(line);
-                            board.apply_domain(domain);
-                            let heuristic = Heuristic::Greedy;
-
-                            let now = Instant::now();
-                            let (result, backtrack_counter) = board.solve_fc(&heuristic);
-                            let now = now.elapsed().as_secs_f64();
-                            let board = format!("{}", board);
-
-                            (idx, now, result, backtrack_counter, board)
-                        })
-                        .collect::<Vec<(usize, f64, char, u64, String)>>();
-
-                    results
-                        .into_iter()
-                        .for_each(|(idx, now, result, backtrack_counter, board)| {
-                            println!(
-                                "{} | {} | {} | {}| {}\n",
-                                idx, now, result, backtrack_counter, board
-                            )
-                        });
-                }
+        Opt::Validate {
+            nth,
+            input,
+            puzzle_column,
+            id_column,
+        } => validate::run(&input, nth, id_column, puzzle_column).map(|valid| {
+            if !valid {
+                process::exit(1);
             }
+        }),
+        Opt::Count {
+            nth,
+            input,
+            limit,
+            search,
+        } => select(&search, MethodName::ForwardChecking)
+            .and_then(|method| count_input(&input, nth, limit, &method, search.solver.seed)),
+        Opt::Solutions {
+            nth,
+            input,
+            limit,
+            output,
+            search,
+        } => select(&search, MethodName::ForwardChecking).and_then(|method| {
+            write_solutions(&input, nth, limit, output, &method, search.solver.seed)
+        }),
+        Opt::Generate {
+            difficulty,
+            seed,
+            count,
+            box_rows,
+            box_cols,
+        } => generate(difficulty, seed, count, box_rows, box_cols),
+        Opt::Rate {
+            nth,
+            input,
+            difficulty_column,
+            id_column,
+        } => {
+            let columns = rate::Columns {
+                id: id_column,
+                puzzle: input.column,
+                difficulty: difficulty_column,
+            };
+            rate::run(&input, nth, &columns)
         }
+        Opt::Explain { nth, input } => explain(&input, nth),
+        Opt::Hint { nth, input } => input.nth(nth as usize).map(|record| {
+            match Sudoku::try_from_flattened(record.field(input.column)) {
+                Ok(board) => match board.hint() {
+                    Some(hint) => println!("{} | {}", nth, hint),
+                    None => println!("{} | no deduction found", nth),
+                },
+                Err(e) => println!("{} | {}", nth, e),
+            }
+        }),
+        Opt::Dimacs { nth, input, output } => write_dimacs(&input, nth, output),
+        Opt::Convert { file, to } => convert(&file, to),
+    };
+
+    if let Err(e) = outcome {
+        eprintln!("{}", e);
+        process::exit(1);
     }
 }
 
-fn run_solve(line: String, domain: BitSet) -> (f64, char, u64, String) {
-    let line = line.split(';').nth(2).unwrap();
-    let mut board = Sudoku::from_flattened(line);
-    board.apply_domain(domain);
-    dbg!(&board.domains);
-    let v_heuristic = Heuristic::Random;
-    let s_heuristic = Heuristic::LeastConstrainedVariable;
-
-    let now = Instant::now();
-    let (result, backtrack_counter) = board.solve(&v_heuristic, &s_heuristic);
-    let now = now.elapsed().as_secs_f64();
-    let board = format!("{}", board);
+fn backtracking(solver: &SolverOpt) -> Method {
+    Method::Backtracking {
+        value_heuristic: solver.value_heuristic.clone().unwrap_or(Heuristic::Random),
+        slot_heuristic: solver
+            .variable_heuristic
+            .clone()
+            .unwrap_or(Heuristic::LeastConstrainedVariable),
+    }
+}
+
+fn forward_checking(solver: &SolverOpt) -> Method {
+    Method::ForwardChecking {
+        value_heuristic: solver.value_heuristic.clone().unwrap_or(Heuristic::Greedy),
+        slot_heuristic: solver
+            .variable_heuristic
+            .clone()
+            .unwrap_or(Heuristic::Greedy),
+    }
+}
+
+fn arc_consistency(solver: &SolverOpt) -> Method {
+    Method::ArcConsistency {
+        value_heuristic: solver.value_heuristic.clone().unwrap_or(Heuristic::Greedy),
+        slot_heuristic: solver
+            .variable_heuristic
+            .clone()
+            .unwrap_or(Heuristic::Greedy),
+    }
+}
+
+// the method asked for with `--method`, dancing links and SAT have no heuristics to choose and
+// draw no random numbers
+fn select(search: &SearchOpt, default: MethodName) -> io::Result<Method> {
+    let solver = &search.solver;
+    match search.method.unwrap_or(default) {
+        MethodName::Backtracking => Ok(backtracking(solver)),
+        MethodName::ForwardChecking => Ok(forward_checking(solver)),
+        MethodName::ArcConsistency => Ok(arc_consistency(solver)),
+        MethodName::DancingLinks | MethodName::Sat
+            if solver.value_heuristic.is_some()
+                || solver.variable_heuristic.is_some()
+                || solver.seed.is_some() =>
+        {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the dlx and sat methods take no heuristics and no seed",
+            ))
+        }
+        MethodName::DancingLinks => Ok(Method::DancingLinks),
+        MethodName::Sat => Ok(Method::Sat),
+    }
+}
+
+fn read_to_string(path: &str) -> io::Result<String> {
+    if path == "-" {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content)?;
+        Ok(content)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+fn run_input(
+    input: &InputOpt,
+    nth: Option<u32>,
+    method: &Method,
+    seed: Option<u64>,
+) -> io::Result<()> {
+    match nth {
+        Some(n) => {
+            let record = input.nth(n as usize)?;
+            print_result(record.line, run(&record, input.column, method, seed));
+        }
+        None => {
+            let results = input
+                .records()?
+                .into_par_iter()
+                .map(|record| (record.line, run(&record, input.column, method, seed)))
+                .collect::<Vec<(usize, RunResult)>>();
+
+            results
+                .into_iter()
+                .for_each(|(idx, result)| print_result(idx, result));
+        }
+    }
+
+    Ok(())
+}
+
+fn count_input(
+    input: &InputOpt,
+    nth: Option<u32>,
+    limit: usize,
+    method: &Method,
+    seed: Option<u64>,
+) -> io::Result<()> {
+    let records = match nth {
+        Some(n) => vec![input.nth(n as usize)?],
+        None => input.records()?,
+    };
 
-    (now, result, backtrack_counter, board)
+    let results = records
+        .into_par_iter()
+        .map(|record| {
+            let count = Sudoku::try_from_flattened(record.field(input.column)).map(|mut board| {
+                board.apply_domain(board.full_domain());
+                board.count_solutions(limit, method, solver::rng(seed))
+            });
+            (record.line, count)
+        })
+        .collect::<Vec<_>>();
+
+    results.into_iter().for_each(|(idx, count)| match count {
+        Ok(count) => println!("{} | {}", idx, count),
+        Err(e) => println!("{} | {}", idx, e),
+    });
+
+    Ok(())
+}
+
+fn write_solutions(
+    input: &InputOpt,
+    nth: u32,
+    limit: Option<usize>,
+    output: Option<String>,
+    method: &Method,
+    seed: Option<u64>,
+) -> io::Result<()> {
+    let record = input.nth(nth as usize)?;
+    let mut board = Sudoku::try_from_flattened(record.field(input.column))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    board.apply_domain(board.full_domain());
+
+    let mut out: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+
+    let solutions = board
+        .solutions_with(method, solver::rng(seed))
+        .take(limit.unwrap_or(usize::MAX));
+    for solution in solutions {
+        writeln!(out, "{}", solution)?;
+    }
+
+    out.flush()
+}
+
+fn convert(file: &str, to: Format) -> io::Result<()> {
+    let content = read_to_string(file)?;
+    let board =
+        format::read(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    // only the multi-line formats end with a newline of their own
+    let converted = format::write(&board, to);
+    if converted.ends_with('\n') {
+        print!("{}", converted);
+    } else {
+        println!("{}", converted);
+    }
+    Ok(())
+}
+
+fn write_dimacs(input: &InputOpt, nth: u32, output: Option<String>) -> io::Result<()> {
+    let record = input.nth(nth as usize)?;
+    let board = Sudoku::try_from_flattened(record.field(input.column))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let mut out: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+    write!(out, "{}", board.to_cnf())?;
+    out.flush()
+}
+
+fn generate(
+    difficulty: f64,
+    seed: Option<u64>,
+    count: u64,
+    box_rows: usize,
+    box_cols: usize,
+) -> io::Result<()> {
+    let size = box_rows * box_cols;
+    if size == 0 || size > MAX_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "boxes of {}x{} give a side of {}, it has to be from 1 to {}",
+                box_rows, box_cols, size, MAX_SIZE
+            ),
+        ));
+    }
+    // the written puzzles keep only their side, every reader takes the boxes from it
+    if (box_rows, box_cols) != box_dimensions(size) {
+        let (rows, cols) = box_dimensions(size);
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "boxes of {}x{} would be read back as {}x{}, the boxes readers assume for a side of {}",
+                box_rows, box_cols, rows, cols, size
+            ),
+        ));
+    }
+
+    let seed = seed.unwrap_or_else(rand::random);
+    eprintln!("seed: {}", seed);
+
+    let puzzles = (0..count)
+        .into_par_iter()
+        .map(|n| generator::generate(box_rows, box_cols, difficulty, seed.wrapping_add(n)))
+        .collect::<Vec<_>>();
+
+    println!("id;difficulty;puzzle;solution");
+    puzzles
+        .into_iter()
+        .enumerate()
+        .for_each(|(idx, generated)| {
+            println!(
+                "{};{:.1};{};{}",
+                idx + 1,
+                generated.difficulty,
+                format::write(&generated.puzzle, Format::Line),
+                generated.solution
+            )
+        });
+
+    Ok(())
+}
+
+fn explain(input: &InputOpt, nth: u32) -> io::Result<()> {
+    let record = input.nth(nth as usize)?;
+    let mut board = match Sudoku::try_from_flattened(record.field(input.column)) {
+        Ok(board) => board,
+        Err(e) => {
+            println!("{} | {}", nth, e);
+            return Ok(());
+        }
+    };
+    board.apply_domain(board.full_domain());
+
+    let steps = board.solve_logically();
+    steps
+        .iter()
+        .enumerate()
+        .for_each(|(n, step)| println!("{}. {}", n + 1, step));
+
+    let status = if board.is_solved() {
+        Status::Solved
+    } else {
+        Status::Unsolved
+    };
+    println!(
+        "\n{} | {} | {}",
+        nth,
+        status,
+        format::write(&board, Format::Line)
+    );
+    Ok(())
+}
+
+fn print_result(idx: usize, result: RunResult) {
+    match result {
+        Ok((now, result, backtrack_counter, backjump_counter, board)) => println!(
+            "{} | {} | {} | {} | {}| {}\n",
+            idx, now, result, backtrack_counter, backjump_counter, board
+        ),
+        Err(e) => println!("{} | {}\n", idx, e),
+    }
 }
 
-fn run_solve_fc(line: String, domain: BitSet) -> (f64, char, u64, String) {
-    let line = line.split(';').nth(2).unwrap();
-    let mut board = Sudoku::from_flattened(line);
-    board.apply_domain(domain);
-    let heuristic = Heuristic::Greedy;
+fn run(record: &Record, column: usize, method: &Method, seed: Option<u64>) -> RunResult {
+    let mut board = Sudoku::try_from_flattened(record.field(column))?;
+    board.apply_domain(board.full_domain());
 
-    let now = Instant::now();
-    let (result, backtrack_counter) = board.solve_fc(&heuristic);
-    let now = now.elapsed().as_secs_f64();
+    let result = board.solve_with(method, solver::rng(seed));
     let board = format!("{}", board);
 
-    (now, result, backtrack_counter, board)
+    Ok((
+        result.elapsed.as_secs_f64(),
+        result.status,
+        result.backtrack_counter,
+        result.backjump_counter,
+        board,
+    ))
 }
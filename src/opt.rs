@@ -1,8 +1,208 @@
 use structopt::StructOpt;
 
+use std::str::FromStr;
+
+use sudoku_solver::{Format, Heuristic};
+
 #[derive(Debug, StructOpt)]
 pub enum Opt {
-    Load { nth: u32 },
-    SolveFc { nth: Option<u32> },
-    Solve { nth: Option<u32> },
+    Load {
+        nth: u32,
+        #[structopt(flatten)]
+        input: InputOpt,
+    },
+    /// Solves the puzzles, with backtracking unless `--method` picks another search
+    Solve {
+        nth: Option<u32>,
+        #[structopt(flatten)]
+        input: InputOpt,
+        #[structopt(flatten)]
+        search: SearchOpt,
+    },
+    /// Solves the puzzles and compares the results with the expected solutions
+    Verify {
+        nth: Option<u32>,
+        #[structopt(flatten)]
+        input: InputOpt,
+        /// Field holding the expected solution, `-` when there is none, counted from 0
+        #[structopt(short, long, default_value = "3")]
+        solution_column: usize,
+        /// Field holding the puzzle id, counted from 0
+        #[structopt(long, default_value = "0")]
+        id_column: usize,
+        #[structopt(flatten)]
+        search: SearchOpt,
+    },
+    /// Checks that the grids selected with `--column` are correct, complete solutions
+    Validate {
+        nth: Option<u32>,
+        #[structopt(flatten)]
+        input: InputOpt,
+        /// Field holding the puzzle whose givens the solution has to keep, counted from 0
+        #[structopt(short, long)]
+        puzzle_column: Option<usize>,
+        /// Field holding the puzzle id, counted from 0
+        #[structopt(long, default_value = "0")]
+        id_column: usize,
+    },
+    /// Counts the solutions of the puzzles, up to the given limit
+    Count {
+        nth: Option<u32>,
+        #[structopt(flatten)]
+        input: InputOpt,
+        /// Stop the search after finding this many solutions
+        #[structopt(short, long, default_value = "2")]
+        limit: usize,
+        #[structopt(flatten)]
+        search: SearchOpt,
+    },
+    /// Streams the solutions of a single puzzle, one per line
+    Solutions {
+        nth: u32,
+        #[structopt(flatten)]
+        input: InputOpt,
+        /// Stop after this many solutions
+        #[structopt(short, long)]
+        limit: Option<usize>,
+        /// File to write the solutions to instead of stdout
+        #[structopt(short, long)]
+        output: Option<String>,
+        #[structopt(flatten)]
+        search: SearchOpt,
+    },
+    /// Generates puzzles with a unique solution, written in the format of `sudoku.csv`
+    Generate {
+        /// Difficulty band of the puzzles, from 0 to 7 on the scale of the difficulty column of
+        /// `sudoku.csv`, 8 and 9 being the puzzles with several solutions and without any
+        #[structopt(short, long, default_value = "0")]
+        difficulty: f64,
+        /// Seed of the first puzzle, every next one uses the following number
+        #[structopt(short, long)]
+        seed: Option<u64>,
+        /// How many puzzles to generate
+        #[structopt(short = "n", long, default_value = "1")]
+        count: u64,
+        /// Height of a box of the board, the boxes have to be the ones readers infer from the side:
+        /// as close to square as possible and not higher than wide, like 2x3
+        #[structopt(long, default_value = "3")]
+        box_rows: usize,
+        /// Width of a box of the board
+        #[structopt(long, default_value = "3")]
+        box_cols: usize,
+    },
+    /// Solves a puzzle step by step with human-style techniques, explaining every deduction
+    Explain {
+        nth: u32,
+        #[structopt(flatten)]
+        input: InputOpt,
+    },
+    /// Shows the simplest deduction that can be made on a puzzle
+    Hint {
+        nth: u32,
+        #[structopt(flatten)]
+        input: InputOpt,
+    },
+    /// Rates the puzzles with the techniques and compares the rating with their difficulty
+    Rate {
+        nth: Option<u32>,
+        #[structopt(flatten)]
+        input: InputOpt,
+        /// Field holding the difficulty to compare with, counted from 0, on the scale of
+        /// `sudoku.csv`, which `generate` writes as well
+        #[structopt(long, default_value = "1")]
+        difficulty_column: usize,
+        /// Field holding the puzzle id, counted from 0
+        #[structopt(long, default_value = "0")]
+        id_column: usize,
+    },
+    /// Writes the CNF encoding of a puzzle in the DIMACS format, for external SAT solvers
+    Dimacs {
+        nth: u32,
+        #[structopt(flatten)]
+        input: InputOpt,
+        /// File to write the formula to instead of stdout
+        #[structopt(short, long)]
+        output: Option<String>,
+    },
+    /// Reads a single puzzle in any supported format and writes it in another one
+    Convert {
+        /// File with the puzzle, `-` reads it from stdin
+        file: String,
+        /// Format of the output: line, grid, ss or sdk
+        #[structopt(short, long, default_value = "grid")]
+        to: Format,
+    },
+}
+
+/// Search algorithm chosen with `--method`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodName {
+    Backtracking,
+    ForwardChecking,
+    ArcConsistency,
+    DancingLinks,
+    Sat,
+}
+
+impl FromStr for MethodName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use MethodName::*;
+
+        match s.to_lowercase().as_str() {
+            "bt" | "backtracking" => Ok(Backtracking),
+            "fc" | "forward-checking" => Ok(ForwardChecking),
+            "ac" | "arc-consistency" => Ok(ArcConsistency),
+            "dlx" | "dancing-links" => Ok(DancingLinks),
+            "sat" => Ok(Sat),
+            _ => Err(format!(
+                "unknown method {}, expected one of: bt, fc, ac, dlx, sat",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct SearchOpt {
+    /// Search algorithm: bt (backtracking), fc (forward checking), ac (arc consistency), dlx
+    /// (dancing links) or sat, forward checking when left out except for `solve`
+    #[structopt(long)]
+    pub method: Option<MethodName>,
+    #[structopt(flatten)]
+    pub solver: SolverOpt,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct SolverOpt {
+    /// Seed of the random heuristics, the same seed always gives the same search
+    #[structopt(long)]
+    pub seed: Option<u64>,
+    /// Order of the values tried in a cell: greedy, random, reverse or lcvalue
+    #[structopt(long, parse(try_from_str = Heuristic::parse_value))]
+    pub value_heuristic: Option<Heuristic>,
+    /// Order of the cells being filled: greedy, random, reverse, mcv, lcvar, degree, mcv-degree
+    /// or dom-wdeg
+    #[structopt(long, parse(try_from_str = Heuristic::parse_variable))]
+    pub variable_heuristic: Option<Heuristic>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct InputOpt {
+    /// File with the puzzles, `-` reads them from stdin
+    #[structopt(short, long, default_value = "sudoku.csv")]
+    pub input: String,
+    /// Separator between the fields of a line
+    #[structopt(short, long, default_value = ";")]
+    pub delimiter: char,
+    /// Field holding the puzzle, counted from 0
+    #[structopt(short, long, default_value = "2")]
+    pub column: usize,
+    /// Always treat the first line as a header
+    #[structopt(long, conflicts_with = "no-header")]
+    pub header: bool,
+    /// Never treat the first line as a header
+    #[structopt(long)]
+    pub no_header: bool,
 }
@@ -0,0 +1,74 @@
+use rayon::prelude::*;
+
+use std::io;
+
+use sudoku_solver::{rating, Rating, Sudoku};
+
+use crate::input::Record;
+use crate::opt::InputOpt;
+
+/// Columns of the record taking part in the rating.
+pub struct Columns {
+    pub id: usize,
+    pub puzzle: usize,
+    pub difficulty: usize,
+}
+
+/// Rates every selected puzzle and compares the rating with the difficulty column, when it holds
+/// a number.
+pub fn run(input: &InputOpt, nth: Option<u32>, columns: &Columns) -> io::Result<()> {
+    let records = match nth {
+        Some(n) => vec![input.nth(n as usize)?],
+        None => input.records()?,
+    };
+
+    let results = records
+        .into_par_iter()
+        .map(|record| {
+            (
+                record.field(columns.id).to_string(),
+                expected(&record, columns),
+                rate(&record, columns),
+            )
+        })
+        .collect::<Vec<(String, Option<f64>, Result<Rating, String>)>>();
+
+    let mut differences = Vec::new();
+    results
+        .iter()
+        .for_each(|(id, expected, rating)| match (rating, expected) {
+            (Ok(rating), Some(expected)) => {
+                differences.push(rating.difficulty - expected);
+                println!("{} | {:.1} | {}", id, expected, rating);
+            }
+            (Ok(rating), None) => println!("{} | - | {}", id, rating),
+            (Err(e), _) => println!("{} | {}", id, e),
+        });
+
+    let compared = differences.len();
+    let matching = differences.iter().filter(|d| d.abs() < 0.5).count();
+    let mean = if compared == 0 {
+        0.0
+    } else {
+        differences.iter().map(|d| d.abs()).sum::<f64>() / compared as f64
+    };
+    println!(
+        "\nrated {} puzzles, {} of {} match the difficulty column, mean absolute difference {:.2}",
+        results.len(),
+        matching,
+        compared,
+        mean
+    );
+
+    Ok(())
+}
+
+fn expected(record: &Record, columns: &Columns) -> Option<f64> {
+    record.field(columns.difficulty).trim().parse().ok()
+}
+
+fn rate(record: &Record, columns: &Columns) -> Result<Rating, String> {
+    let puzzle =
+        Sudoku::try_from_flattened(record.field(columns.puzzle)).map_err(|e| e.to_string())?;
+    Ok(rating::rate(&puzzle))
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::board::{Sudoku, Technique};
+use crate::solver::{Method, SolutionCount};
+
+/// The hardest difficulty, given to the puzzles without any solution.
+pub const MAX_DIFFICULTY: f64 = 9.0;
+
+/// The hardest difficulty of a puzzle with a unique solution, the ones with more than one get
+/// the band above it.
+pub const MAX_UNIQUE_DIFFICULTY: f64 = 7.0;
+
+/// Band of the puzzles solved by the techniques, by the rung of the hardest one needed.
+const SOLVED_BANDS: [f64; 9] = [0.0, 0.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0, 2.0];
+
+/// Cells left empty out of 81 from which a puzzle the techniques get stuck on moves up a band,
+/// starting from band 3.
+const STUCK_THRESHOLDS: [usize; 4] = [25, 35, 45, 55];
+
+/// Difficulty of a puzzle on the scale of the `difficulty` column of `sudoku.csv`, from 0 to
+/// `MAX_DIFFICULTY`.
+///
+/// Puzzles the techniques solve get the band of the hardest technique needed, the ones they get
+/// stuck on a band growing with the cells left empty, puzzles with several solutions 8 and the
+/// ones without any 9. The bands are fitted to the column, which they match on 12 of its 44
+/// puzzles, missing it by 1.6 on average.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rating {
+    /// Band of the puzzle, from 0 to `MAX_DIFFICULTY`
+    pub difficulty: f64,
+    /// Hardest technique used, `None` when no deduction could be made
+    pub hardest: Option<Technique>,
+    /// Number of deductions made
+    pub steps: usize,
+    /// Whether the techniques solved the whole puzzle
+    pub solved: bool,
+    /// Cells still empty once the techniques are done
+    pub left: usize,
+    /// Solutions of the puzzle, counted up to two
+    pub solutions: SolutionCount,
+}
+
+impl Technique {
+    /// Rung of the technique on the ladder of `Technique::ALL`, from 0 for hidden singles to 8
+    /// for simple coloring.
+    pub fn rung(self) -> usize {
+        use Technique::*;
+
+        match self {
+            HiddenSingle => 0,
+            NakedSingle => 1,
+            PointingPair | BoxLineReduction => 2,
+            NakedPair | HiddenPair => 3,
+            NakedTriple | HiddenTriple => 4,
+            XWing | NakedQuad => 5,
+            HiddenQuad | Swordfish => 6,
+            XYWing => 7,
+            SimpleColoring => 8,
+        }
+    }
+}
+
+/// Rates the puzzle by solving it with the techniques, always taking the simplest deduction, and
+/// counting its solutions.
+pub fn rate(puzzle: &Sudoku) -> Rating {
+    let mut board = puzzle.clone();
+    board.apply_domain(board.full_domain());
+    // dancing links draws no random numbers
+    let solutions = board.count_solutions(2, &Method::DancingLinks, StdRng::seed_from_u64(0));
+
+    let mut hardest: Option<Technique> = None;
+    let mut steps = 0;
+    while let Some(step) = board.next_step() {
+        board.apply_step(&step);
+        steps += 1;
+        if hardest.is_none_or(|hardest| step.technique.rung() > hardest.rung()) {
+            hardest = Some(step.technique);
+        }
+    }
+
+    let solved = board.is_solved();
+    let left = board.board.iter().filter(|&&value| value == 0).count();
+    let difficulty = match solutions {
+        SolutionCount::Exactly(0) => MAX_DIFFICULTY,
+        SolutionCount::Exactly(1) if solved => {
+            SOLVED_BANDS[hardest.map(Technique::rung).unwrap_or(0)]
+        }
+        SolutionCount::Exactly(1) => {
+            // scaled to a 9x9 board, so that the thresholds hold for any size
+            let left = left * 81 / board.board.len();
+            let above = STUCK_THRESHOLDS
+                .iter()
+                .filter(|&&threshold| left >= threshold)
+                .count();
+            3.0 + above as f64
+        }
+        _ => MAX_UNIQUE_DIFFICULTY + 1.0,
+    };
+
+    Rating {
+        difficulty,
+        hardest,
+        steps,
+        solved,
+        left,
+        solutions,
+    }
+}
+
+impl fmt::Display for Rating {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1}", self.difficulty)?;
+        match self.hardest {
+            Some(technique) if self.solved => write!(f, " ({})", technique),
+            _ if self.solutions == SolutionCount::Exactly(0) => write!(f, " (no solution)"),
+            _ if self.solutions != SolutionCount::Exactly(1) => {
+                write!(f, " (several solutions)")
+            }
+            Some(technique) => write!(
+                f,
+                " (stuck with {} cells left, hardest {})",
+                self.left, technique
+            ),
+            None if self.solved => Ok(()),
+            None => write!(f, " (stuck with {} cells left)", self.left),
+        }
+    }
+}
@@ -0,0 +1,473 @@
+use std::fmt;
+use std::mem;
+
+/// Formula in conjunctive normal form, with the variables and literals numbered as in DIMACS:
+/// variables count from 1 and a negative literal is the negation of its variable.
+#[derive(Debug, Clone, Default)]
+pub struct Cnf {
+    pub variables: usize,
+    pub clauses: Vec<Vec<i32>>,
+    /// Lines written as `c` comments in front of the formula
+    pub comments: Vec<String>,
+}
+
+/// Conflict driven clause learning solver, with two watched literals per clause, first UIP
+/// learning and activity based branching.
+///
+/// Clauses can be added between two calls to `solve`, so that the same formula can be solved
+/// again with some of its models blocked.
+#[derive(Debug, Clone)]
+pub struct Solver {
+    /// Literals of every clause, the first two of them are watched
+    clauses: Vec<Vec<Literal>>,
+    /// Clauses watching every literal, visited when it becomes false
+    watches: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+    /// Decision level at which every variable was assigned
+    levels: Vec<usize>,
+    /// Clause which implied the value of every variable, none for the decisions
+    reasons: Vec<Option<usize>>,
+    /// Assigned literals in order, with the start of every decision level
+    trail: Vec<Literal>,
+    trail_limits: Vec<usize>,
+    /// Position of the next literal of the trail to propagate
+    head: usize,
+    activity: Vec<f64>,
+    increment: f64,
+    /// Value taken by every variable last time, tried first when branching on it again
+    phases: Vec<bool>,
+    conflicts: u64,
+    /// Whether the formula became unsatisfiable at level 0
+    unsatisfiable: bool,
+}
+
+/// Variable and sign packed together, `2 * variable + 1` for the negation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Literal(usize);
+
+const ACTIVITY_DECAY: f64 = 0.95;
+const ACTIVITY_LIMIT: f64 = 1e100;
+
+impl Literal {
+    fn from_dimacs(literal: i32) -> Self {
+        let variable = literal.unsigned_abs() as usize - 1;
+        Literal(2 * variable + (literal < 0) as usize)
+    }
+
+    fn variable(self) -> usize {
+        self.0 / 2
+    }
+
+    fn is_negated(self) -> bool {
+        self.0 % 2 == 1
+    }
+
+    fn negate(self) -> Self {
+        Literal(self.0 ^ 1)
+    }
+}
+
+impl Cnf {
+    pub fn new(variables: usize) -> Self {
+        Self {
+            variables,
+            ..Self::default()
+        }
+    }
+
+    pub fn add_clause(&mut self, clause: Vec<i32>) {
+        self.clauses.push(clause);
+    }
+}
+
+impl fmt::Display for Cnf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for comment in &self.comments {
+            writeln!(f, "c {}", comment)?;
+        }
+        writeln!(f, "p cnf {} {}", self.variables, self.clauses.len())?;
+        for clause in &self.clauses {
+            for literal in clause {
+                write!(f, "{} ", literal)?;
+            }
+            writeln!(f, "0")?;
+        }
+        Ok(())
+    }
+}
+
+impl Solver {
+    pub fn new(cnf: &Cnf) -> Self {
+        let mut solver = Self {
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); 2 * cnf.variables],
+            values: vec![None; cnf.variables],
+            levels: vec![0; cnf.variables],
+            reasons: vec![None; cnf.variables],
+            trail: Vec::new(),
+            trail_limits: Vec::new(),
+            head: 0,
+            activity: vec![0.0; cnf.variables],
+            increment: 1.0,
+            phases: vec![false; cnf.variables],
+            conflicts: 0,
+            unsatisfiable: false,
+        };
+        for clause in &cnf.clauses {
+            solver.add_clause(clause);
+        }
+        solver
+    }
+
+    /// Number of conflicts the search ran into so far.
+    pub fn conflicts(&self) -> u64 {
+        self.conflicts
+    }
+
+    /// Adds a clause, in DIMACS literals, taking back every decision made so far.
+    pub fn add_clause(&mut self, clause: &[i32]) {
+        self.cancel_until(0);
+        if self.unsatisfiable {
+            return;
+        }
+
+        let mut literals: Vec<Literal> = Vec::with_capacity(clause.len());
+        for &literal in clause {
+            let literal = Literal::from_dimacs(literal);
+            match self.value(literal) {
+                Some(true) => return,
+                Some(false) => continue,
+                None => {}
+            }
+            if literals.contains(&literal.negate()) {
+                return;
+            }
+            if !literals.contains(&literal) {
+                literals.push(literal);
+            }
+        }
+
+        match literals.len() {
+            0 => self.unsatisfiable = true,
+            1 => {
+                self.assign(literals[0], None);
+                if self.propagate().is_some() {
+                    self.unsatisfiable = true;
+                }
+            }
+            _ => {
+                self.attach(literals);
+            }
+        }
+    }
+
+    /// Searches for a model, returns the value of every variable, counted from 0, or `None`
+    /// when the formula is unsatisfiable.
+    pub fn solve(&mut self) -> Option<Vec<bool>> {
+        if self.unsatisfiable {
+            return None;
+        }
+
+        loop {
+            match self.propagate() {
+                Some(conflict) => {
+                    self.conflicts += 1;
+                    if self.trail_limits.is_empty() {
+                        self.unsatisfiable = true;
+                        return None;
+                    }
+
+                    let (learnt, level) = self.analyze(conflict);
+                    self.cancel_until(level);
+                    let asserting = learnt[0];
+                    if learnt.len() == 1 {
+                        self.assign(asserting, None);
+                    } else {
+                        let clause = self.attach(learnt);
+                        self.assign(asserting, Some(clause));
+                    }
+                    self.decay_activity();
+                }
+                None => match self.pick_branch() {
+                    Some(literal) => {
+                        self.trail_limits.push(self.trail.len());
+                        self.assign(literal, None);
+                    }
+                    None => {
+                        return Some(
+                            self.values
+                                .iter()
+                                .map(|&value| value == Some(true))
+                                .collect(),
+                        )
+                    }
+                },
+            }
+        }
+    }
+
+    fn value(&self, literal: Literal) -> Option<bool> {
+        self.values[literal.variable()].map(|value| value != literal.is_negated())
+    }
+
+    fn assign(&mut self, literal: Literal, reason: Option<usize>) {
+        let variable = literal.variable();
+        self.values[variable] = Some(!literal.is_negated());
+        self.levels[variable] = self.trail_limits.len();
+        self.reasons[variable] = reason;
+        self.trail.push(literal);
+    }
+
+    // stores a clause of at least two literals, watching the first two of them
+    fn attach(&mut self, literals: Vec<Literal>) -> usize {
+        let clause = self.clauses.len();
+        self.watches[literals[0].0].push(clause);
+        self.watches[literals[1].0].push(clause);
+        self.clauses.push(literals);
+        clause
+    }
+
+    // assigns the literals implied by the trail, returns a clause left with every literal false
+    fn propagate(&mut self) -> Option<usize> {
+        while self.head < self.trail.len() {
+            let falsified = self.trail[self.head].negate();
+            self.head += 1;
+
+            let mut watching = mem::take(&mut self.watches[falsified.0]);
+            let mut i = 0;
+            let mut conflict = None;
+            while i < watching.len() {
+                let clause = watching[i];
+                // keep the falsified literal second, the first one is implied if nothing else is
+                if self.clauses[clause][0] == falsified {
+                    self.clauses[clause].swap(0, 1);
+                }
+                let first = self.clauses[clause][0];
+                if self.value(first) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+
+                let replacement = (2..self.clauses[clause].len())
+                    .find(|&k| self.value(self.clauses[clause][k]) != Some(false));
+                if let Some(k) = replacement {
+                    self.clauses[clause].swap(1, k);
+                    self.watches[self.clauses[clause][1].0].push(clause);
+                    watching.swap_remove(i);
+                    continue;
+                }
+
+                if self.value(first) == Some(false) {
+                    conflict = Some(clause);
+                    break;
+                }
+                self.assign(first, Some(clause));
+                i += 1;
+            }
+            self.watches[falsified.0].extend(watching);
+
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    // learns the first UIP clause of the conflict, with the asserting literal first and one of
+    // the highest level second, together with the level to jump back to
+    fn analyze(&mut self, conflict: usize) -> (Vec<Literal>, usize) {
+        let level = self.trail_limits.len();
+        let mut seen = vec![false; self.values.len()];
+        let mut learnt = vec![Literal(0)];
+        // literals of the current level still to resolve
+        let mut pending = 0;
+        let mut clause = conflict;
+        let mut index = self.trail.len();
+        let mut implied: Option<Literal> = None;
+
+        loop {
+            // the first literal of a reason is the one it implied
+            let skip = implied.is_some() as usize;
+            for k in skip..self.clauses[clause].len() {
+                let literal = self.clauses[clause][k];
+                let variable = literal.variable();
+                if seen[variable] || self.levels[variable] == 0 {
+                    continue;
+                }
+                seen[variable] = true;
+                self.bump_activity(variable);
+                if self.levels[variable] == level {
+                    pending += 1;
+                } else {
+                    learnt.push(literal);
+                }
+            }
+
+            // the last seen literal of the trail is the next to resolve
+            loop {
+                index -= 1;
+                if seen[self.trail[index].variable()] {
+                    break;
+                }
+            }
+            let literal = self.trail[index];
+            seen[literal.variable()] = false;
+            implied = Some(literal);
+            pending -= 1;
+            if pending == 0 {
+                break;
+            }
+            clause = self.reasons[literal.variable()].expect("only the decision has no reason");
+        }
+
+        learnt[0] = implied
+            .expect("the conflict has a literal of its level")
+            .negate();
+        let mut backjump = 0;
+        if learnt.len() > 1 {
+            let highest = (1..learnt.len())
+                .max_by_key(|&k| self.levels[learnt[k].variable()])
+                .unwrap_or(1);
+            learnt.swap(1, highest);
+            backjump = self.levels[learnt[1].variable()];
+        }
+        (learnt, backjump)
+    }
+
+    // takes back the assignments of the levels above the given one
+    fn cancel_until(&mut self, level: usize) {
+        if self.trail_limits.len() <= level {
+            return;
+        }
+
+        let start = self.trail_limits[level];
+        for literal in self.trail.drain(start..) {
+            let variable = literal.variable();
+            self.phases[variable] = !literal.is_negated();
+            self.values[variable] = None;
+            self.reasons[variable] = None;
+        }
+        self.trail_limits.truncate(level);
+        self.head = self.trail.len();
+    }
+
+    // the unassigned variable with the highest activity, with the value it had last time
+    fn pick_branch(&self) -> Option<Literal> {
+        (0..self.values.len())
+            .filter(|&variable| self.values[variable].is_none())
+            .fold(None, |best: Option<usize>, variable| match best {
+                Some(best) if self.activity[best] >= self.activity[variable] => Some(best),
+                _ => Some(variable),
+            })
+            .map(|variable| Literal(2 * variable + !self.phases[variable] as usize))
+    }
+
+    fn bump_activity(&mut self, variable: usize) {
+        self.activity[variable] += self.increment;
+        if self.activity[variable] > ACTIVITY_LIMIT {
+            for activity in &mut self.activity {
+                *activity /= ACTIVITY_LIMIT;
+            }
+            self.increment /= ACTIVITY_LIMIT;
+        }
+    }
+
+    // newer conflicts weigh more, so the increment grows instead of every activity shrinking
+    fn decay_activity(&mut self) {
+        self.increment /= ACTIVITY_DECAY;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cnf(variables: usize, clauses: &[&[i32]]) -> Cnf {
+        let mut cnf = Cnf::new(variables);
+        for clause in clauses {
+            cnf.add_clause(clause.to_vec());
+        }
+        cnf
+    }
+
+    fn satisfies(model: &[bool], cnf: &Cnf) -> bool {
+        cnf.clauses.iter().all(|clause| {
+            clause
+                .iter()
+                .any(|&literal| model[literal.unsigned_abs() as usize - 1] == (literal > 0))
+        })
+    }
+
+    #[test]
+    fn finds_a_model() {
+        let cnf = cnf(3, &[&[1, 2], &[-1], &[-2, 3]]);
+        let model = Solver::new(&cnf).solve().unwrap();
+        assert_eq!(model, vec![false, true, true]);
+        assert!(satisfies(&model, &cnf));
+    }
+
+    #[test]
+    fn refutes_a_contradiction() {
+        assert_eq!(Solver::new(&cnf(1, &[&[1], &[-1]])).solve(), None);
+        assert_eq!(Solver::new(&cnf(1, &[&[]])).solve(), None);
+    }
+
+    #[test]
+    fn refutes_the_pigeonhole_principle() {
+        // three pigeons in two holes, variable 2 * pigeon + hole + 1 puts the pigeon in the hole
+        let mut formula = Cnf::new(6);
+        for pigeon in 0..3 {
+            formula.add_clause(vec![2 * pigeon + 1, 2 * pigeon + 2]);
+        }
+        for hole in 1..=2 {
+            for first in 0..3 {
+                for second in first + 1..3 {
+                    formula.add_clause(vec![-(2 * first + hole), -(2 * second + hole)]);
+                }
+            }
+        }
+
+        let mut solver = Solver::new(&formula);
+        assert_eq!(solver.solve(), None);
+        assert!(solver.conflicts() > 0);
+    }
+
+    #[test]
+    fn blocks_the_models_found() {
+        // exactly one of three variables
+        let cnf = cnf(3, &[&[1, 2, 3], &[-1, -2], &[-1, -3], &[-2, -3]]);
+        let mut solver = Solver::new(&cnf);
+        let mut models = Vec::new();
+        while let Some(model) = solver.solve() {
+            assert!(satisfies(&model, &cnf));
+            let blocking: Vec<i32> = (1..=3)
+                .map(|variable| {
+                    if model[variable as usize - 1] {
+                        -variable
+                    } else {
+                        variable
+                    }
+                })
+                .collect();
+            solver.add_clause(&blocking);
+            models.push(model);
+        }
+
+        models.sort();
+        assert_eq!(
+            models,
+            vec![
+                vec![false, false, true],
+                vec![false, true, false],
+                vec![true, false, false],
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_dimacs() {
+        let mut cnf = cnf(2, &[&[1, -2], &[2]]);
+        cnf.comments.push("example".to_string());
+        assert_eq!(cnf.to_string(), "c example\np cnf 2 2\n1 -2 0\n2 0\n");
+    }
+}
@@ -0,0 +1,114 @@
+use std::fmt;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::board::Sudoku;
+use crate::heuristic::Heuristic;
+
+static SOLVED_CHARACTER: char = '\u{2714}';
+static UNSOLVED_CHARACTER: char = '\u{2718}';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Solved,
+    Unsolved,
+}
+
+/// The outcome of a single solver run.
+#[derive(Debug, Clone)]
+pub struct SolveResult {
+    /// The board as it was left by the solver, 0 means an empty cell
+    pub solution: Vec<u32>,
+    pub status: Status,
+    pub backtrack_counter: u64,
+    /// Backtracks which went back over more than one slot, only counted by backtracking
+    pub backjump_counter: u64,
+    pub elapsed: Duration,
+}
+
+/// Number of solutions found by `Sudoku::count_solutions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolutionCount {
+    Exactly(usize),
+    /// The search stopped at the limit, there may be more solutions
+    AtLeast(usize),
+}
+
+/// Solving algorithm together with the heuristics it should use.
+#[derive(Debug, Clone)]
+pub enum Method {
+    Backtracking {
+        value_heuristic: Heuristic,
+        slot_heuristic: Heuristic,
+    },
+    ForwardChecking {
+        value_heuristic: Heuristic,
+        slot_heuristic: Heuristic,
+    },
+    /// Keeps the domains arc consistent after every assignment, with AC-3
+    ArcConsistency {
+        value_heuristic: Heuristic,
+        slot_heuristic: Heuristic,
+    },
+    /// Solves the board as an exact cover problem with Algorithm X on dancing links, always
+    /// branching on the constraint with the fewest candidates left
+    DancingLinks,
+    /// Solves the CNF encoding of the board with the built-in CDCL solver, the backtrack counter
+    /// counts its conflicts
+    Sat,
+}
+
+/// Solves the board in place with the given method.
+///
+/// The board is expected to have its domains applied already (see `Sudoku::apply_domain`).
+pub fn solve(sudoku: &mut Sudoku, method: &Method) -> SolveResult {
+    sudoku.solve_with(method, StdRng::from_entropy())
+}
+
+/// Solves the board like `solve`, drawing the random choices from a generator seeded with `seed`.
+///
+/// The same seed always gives the same search, and so the same backtrack counter.
+pub fn solve_seeded(sudoku: &mut Sudoku, method: &Method, seed: u64) -> SolveResult {
+    sudoku.solve_with(method, StdRng::seed_from_u64(seed))
+}
+
+/// Creates the random number generator for the solver, seeded if the seed is given.
+pub fn rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+impl SolveResult {
+    pub fn is_solved(&self) -> bool {
+        self.status == Status::Solved
+    }
+}
+
+impl SolutionCount {
+    /// Checks whether the puzzle is well-formed, having exactly one solution.
+    pub fn is_unique(self) -> bool {
+        self == SolutionCount::Exactly(1)
+    }
+}
+
+impl fmt::Display for SolutionCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolutionCount::Exactly(n) => write!(f, "{}", n),
+            SolutionCount::AtLeast(n) => write!(f, "at least {}", n),
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Solved => write!(f, "{}", SOLVED_CHARACTER),
+            Status::Unsolved => write!(f, "{}", UNSOLVED_CHARACTER),
+        }
+    }
+}
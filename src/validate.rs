@@ -0,0 +1,71 @@
+use rayon::prelude::*;
+
+use std::io;
+
+use sudoku_solver::Sudoku;
+
+use crate::input::Record;
+use crate::opt::InputOpt;
+
+/// Validates every selected grid, optionally as the solution of the puzzle in `puzzle_column`.
+///
+/// Returns whether all grids are valid.
+pub fn run(
+    input: &InputOpt,
+    nth: Option<u32>,
+    id_column: usize,
+    puzzle_column: Option<usize>,
+) -> io::Result<bool> {
+    let records = match nth {
+        Some(n) => vec![input.nth(n as usize)?],
+        None => input.records()?,
+    };
+
+    let results = records
+        .into_par_iter()
+        .map(|record| {
+            (
+                record.field(id_column).to_string(),
+                validate(&record, input.column, puzzle_column),
+            )
+        })
+        .collect::<Vec<(String, Result<(), String>)>>();
+
+    let mut invalid = 0;
+    for (id, result) in &results {
+        match result {
+            Ok(()) => println!("{} | valid", id),
+            Err(reason) => {
+                println!("{} | INVALID | {}", id, reason);
+                invalid += 1;
+            }
+        }
+    }
+
+    println!("\nvalidated {} grids, {} invalid", results.len(), invalid);
+
+    Ok(invalid == 0)
+}
+
+fn validate(record: &Record, column: usize, puzzle_column: Option<usize>) -> Result<(), String> {
+    let grid = Sudoku::try_from_flattened_unchecked(record.field(column))
+        .map_err(|e| format!("invalid grid: {}", e))?;
+
+    let validation = match puzzle_column {
+        Some(puzzle_column) => {
+            let puzzle = Sudoku::try_from_flattened(record.field(puzzle_column))
+                .map_err(|e| format!("invalid puzzle: {}", e))?;
+            if puzzle.domain_size != grid.domain_size {
+                return Err("the grid and the puzzle differ in size".to_string());
+            }
+            grid.validate_solution_of(&puzzle)
+        }
+        None => grid.validate(),
+    };
+
+    if validation.is_valid() {
+        Ok(())
+    } else {
+        Err(validation.to_string())
+    }
+}
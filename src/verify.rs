@@ -0,0 +1,134 @@
+use rayon::prelude::*;
+
+use std::io;
+
+use sudoku_solver::{solver, Method, Sudoku};
+
+use crate::input::Record;
+use crate::opt::InputOpt;
+
+/// Outcome of verifying a single puzzle.
+enum Verdict {
+    Passed,
+    /// There was nothing to compare the result with
+    Skipped(String),
+    Failed(String),
+}
+
+/// What the solution field of a record says about the puzzle.
+enum Expected {
+    /// The field is empty, any solution following the rules passes
+    Unknown,
+    /// The field holds `NO_SOLUTION`, the solver must not report one
+    NoSolution,
+    Solution(Box<Sudoku>),
+}
+
+/// Marks a puzzle which has no solution in the solution field.
+const NO_SOLUTION: &str = "-";
+
+/// Columns of the record taking part in the verification.
+pub struct Columns {
+    pub id: usize,
+    pub puzzle: usize,
+    pub solution: usize,
+}
+
+/// Solves every selected puzzle and compares it with the expected solution.
+///
+/// When the solution field is empty, the result is only checked against the rules of sudoku, when
+/// it is `-`, the puzzle must be reported as unsolved.
+/// Returns whether no puzzle failed.
+pub fn run(
+    input: &InputOpt,
+    nth: Option<u32>,
+    columns: &Columns,
+    method: &Method,
+    seed: Option<u64>,
+) -> io::Result<bool> {
+    let records = match nth {
+        Some(n) => vec![input.nth(n as usize)?],
+        None => input.records()?,
+    };
+
+    let results = records
+        .into_par_iter()
+        .map(|record| {
+            (
+                record.field(columns.id).to_string(),
+                verify(&record, columns, method, seed),
+            )
+        })
+        .collect::<Vec<(String, Verdict)>>();
+
+    let (mut skipped, mut failed) = (0, 0);
+    results.iter().for_each(|(id, verdict)| match verdict {
+        Verdict::Passed => println!("{} | ok", id),
+        Verdict::Skipped(reason) => {
+            skipped += 1;
+            println!("{} | skipped | {}", id, reason);
+        }
+        Verdict::Failed(reason) => {
+            failed += 1;
+            println!("{} | FAILED | {}", id, reason);
+        }
+    });
+
+    println!(
+        "\nverified {} puzzles, {} skipped, {} failed",
+        results.len(),
+        skipped,
+        failed
+    );
+
+    Ok(failed == 0)
+}
+
+fn verify(record: &Record, columns: &Columns, method: &Method, seed: Option<u64>) -> Verdict {
+    let expected = match record.field(columns.solution).trim() {
+        "" => Expected::Unknown,
+        NO_SOLUTION => Expected::NoSolution,
+        solution => match Sudoku::try_from_flattened(solution) {
+            Ok(expected) => Expected::Solution(Box::new(expected)),
+            Err(e) => return Verdict::Failed(format!("invalid expected solution: {}", e)),
+        },
+    };
+
+    let mut board = match Sudoku::try_from_flattened(record.field(columns.puzzle)) {
+        Ok(board) => board,
+        Err(e) => {
+            return match expected {
+                Expected::Solution(_) => Verdict::Failed(format!("puzzle rejected: {}", e)),
+                _ => Verdict::Skipped(format!("puzzle rejected: {}", e)),
+            }
+        }
+    };
+    board.apply_domain(board.full_domain());
+
+    let result = board.solve_with(method, solver::rng(seed));
+    match expected {
+        Expected::NoSolution if result.is_solved() => {
+            Verdict::Failed(format!("found a solution where none exists: {}", board))
+        }
+        Expected::NoSolution => Verdict::Passed,
+        Expected::Unknown if !result.is_solved() => {
+            Verdict::Skipped("no solution found".to_string())
+        }
+        Expected::Solution(_) if !result.is_solved() => {
+            Verdict::Failed("no solution found".to_string())
+        }
+        _ if !board.is_solved() => Verdict::Failed(format!("solution breaks the rules: {}", board)),
+        Expected::Solution(expected) if expected.board != board.board => {
+            let cells = board
+                .board
+                .iter()
+                .zip(expected.board.iter())
+                .enumerate()
+                .filter(|(_, (found, expected))| found != expected)
+                .map(|(idx, _)| idx)
+                .collect::<Vec<_>>();
+            Verdict::Failed(format!("solution differs at cells {:?}: {}", cells, board))
+        }
+        _ => Verdict::Passed,
+    }
+}
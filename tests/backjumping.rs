@@ -0,0 +1,75 @@
+use std::fs;
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use sudoku_solver::{solve_seeded, Heuristic, Method, Sudoku};
+
+// puzzle of the given line of a csv file in the crate, with its domains applied
+fn puzzle(file: &str, id: usize) -> Sudoku {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(file);
+    let text = fs::read_to_string(path).expect("the csv file is readable");
+    let line = text.lines().nth(id).expect("the csv file has the puzzle");
+    let puzzle = line.split(';').nth(2).expect("line has a puzzle field");
+    let mut board = Sudoku::try_from_flattened(puzzle).expect("puzzle parses");
+    board.apply_domain(board.full_domain());
+    board
+}
+
+fn backtracking(slot_heuristic: Heuristic) -> Method {
+    Method::Backtracking {
+        value_heuristic: Heuristic::Greedy,
+        slot_heuristic,
+    }
+}
+
+// every solution of the board, sorted so that searches in another order compare equal
+fn solutions(board: &Sudoku, method: &Method) -> Vec<Vec<u32>> {
+    let mut solutions = board
+        .solutions_with(method, StdRng::seed_from_u64(7))
+        .map(|solution| solution.board)
+        .collect::<Vec<_>>();
+    solutions.sort();
+    solutions
+}
+
+// puzzles 41 to 43 of `sudoku.csv` have 46, 40 and 87 solutions, 44 has none
+#[test]
+fn backjumping_finds_the_solutions_of_chronological_backtracking() {
+    for &(id, count) in &[(1, 1), (41, 46), (42, 40), (43, 87), (44, 0)] {
+        let board = puzzle("sudoku.csv", id);
+        // forward checking still steps back one slot at a time
+        let expected = solutions(
+            &board,
+            &Method::ForwardChecking {
+                value_heuristic: Heuristic::Greedy,
+                slot_heuristic: Heuristic::MostConstrainedVariable,
+            },
+        );
+        assert_eq!(expected.len(), count, "puzzle {}", id);
+
+        for slot_heuristic in &[Heuristic::Greedy, Heuristic::MostConstrainedVariable] {
+            let method = backtracking(slot_heuristic.clone());
+            assert_eq!(solutions(&board, &method), expected, "puzzle {}", id);
+        }
+    }
+}
+
+#[test]
+fn counts_the_backjumps_of_backtracking_only() {
+    // in row order, the search on puzzle 6 runs into slots left without values by much earlier
+    // ones
+    let mut board = puzzle("sudoku.csv", 6);
+    let result = solve_seeded(&mut board.clone(), &backtracking(Heuristic::Greedy), 7);
+    assert!(result.is_solved());
+    assert!(result.backjump_counter > 0);
+    assert!(result.backjump_counter < result.backtrack_counter);
+
+    let method = Method::ForwardChecking {
+        value_heuristic: Heuristic::Greedy,
+        slot_heuristic: Heuristic::Greedy,
+    };
+    let result = solve_seeded(&mut board, &method, 7);
+    assert!(result.backtrack_counter > 0);
+    assert_eq!(result.backjump_counter, 0);
+}
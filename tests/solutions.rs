@@ -0,0 +1,124 @@
+use std::fs;
+use std::path::Path;
+
+use sudoku_solver::solver::rng;
+use sudoku_solver::{Heuristic, Method, SolutionCount, Sudoku};
+
+// puzzle of the given line of a csv file in the crate, with its domains applied
+fn puzzle(file: &str, id: usize) -> Sudoku {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(file);
+    let text = fs::read_to_string(path).expect("the csv file is readable");
+    let line = text.lines().nth(id).expect("the csv file has the puzzle");
+    let puzzle = line.split(';').nth(2).expect("line has a puzzle field");
+    let mut board = Sudoku::try_from_flattened(puzzle).expect("puzzle parses");
+    board.apply_domain(board.full_domain());
+    board
+}
+
+fn methods() -> Vec<Method> {
+    vec![
+        Method::Backtracking {
+            value_heuristic: Heuristic::Random,
+            slot_heuristic: Heuristic::MostConstrainedVariable,
+        },
+        Method::ForwardChecking {
+            value_heuristic: Heuristic::Random,
+            slot_heuristic: Heuristic::MostConstrainedVariable,
+        },
+        Method::ArcConsistency {
+            value_heuristic: Heuristic::Greedy,
+            slot_heuristic: Heuristic::Greedy,
+        },
+        Method::DancingLinks,
+        Method::Sat,
+    ]
+}
+
+// puzzle 1 of `sudoku.csv` is unique and puzzle 42 has 40 solutions
+#[test]
+fn counts_solutions_up_to_the_limit() {
+    let unsolvable = puzzle("unsolvable.csv", 1);
+    let unique = puzzle("sudoku.csv", 1);
+    let several = puzzle("sudoku.csv", 42);
+
+    for method in &methods() {
+        let count = |board: &Sudoku, limit| board.count_solutions(limit, method, rng(Some(7)));
+        assert_eq!(
+            count(&unsolvable, 2),
+            SolutionCount::Exactly(0),
+            "{:?}",
+            method
+        );
+        assert_eq!(count(&unique, 2), SolutionCount::Exactly(1), "{:?}", method);
+        assert_eq!(
+            count(&several, 2),
+            SolutionCount::AtLeast(2),
+            "{:?}",
+            method
+        );
+        assert_eq!(
+            count(&several, 40),
+            SolutionCount::AtLeast(40),
+            "{:?}",
+            method
+        );
+        assert_eq!(
+            count(&several, 41),
+            SolutionCount::Exactly(40),
+            "{:?}",
+            method
+        );
+    }
+}
+
+#[test]
+fn counting_leaves_the_board_untouched() {
+    let board = puzzle("sudoku.csv", 42);
+    let before = board.board.clone();
+    board.count_solutions(10, &Method::DancingLinks, rng(Some(7)));
+    assert_eq!(board.board, before);
+}
+
+#[test]
+fn takes_only_the_solutions_asked_for() {
+    let board = puzzle("sudoku.csv", 42);
+    for method in &methods() {
+        let mut all = board.solutions_with(method, rng(Some(7)));
+        assert_eq!(all.by_ref().count(), 40, "{:?}", method);
+
+        let mut first = board.solutions_with(method, rng(Some(7)));
+        assert_eq!(first.by_ref().take(3).count(), 3, "{:?}", method);
+        assert!(
+            first.backtrack_counter() < all.backtrack_counter(),
+            "{:?} searched past the third solution",
+            method
+        );
+
+        // the search picks up after the third solution
+        assert_eq!(first.count(), 37, "{:?}", method);
+    }
+}
+
+#[test]
+fn yields_every_solution_once() {
+    let board = puzzle("sudoku.csv", 41);
+    let mut solutions = board
+        .solutions()
+        .inspect(|solution| {
+            assert!(solution.board.iter().all(|&value| value != 0));
+            assert!(solution.conflicts().is_empty());
+        })
+        .map(|solution| solution.board)
+        .collect::<Vec<_>>();
+    solutions.sort();
+    solutions.dedup();
+    assert_eq!(solutions.len(), 46);
+}
+
+#[test]
+fn yields_nothing_without_a_solution() {
+    let board = puzzle("unsolvable.csv", 1);
+    let mut solutions = board.solutions();
+    assert!(solutions.next().is_none());
+    assert!(solutions.next().is_none());
+}
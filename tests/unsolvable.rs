@@ -0,0 +1,101 @@
+use std::fs;
+use std::path::Path;
+
+use sudoku_solver::{solve_seeded, Heuristic, Method, Status, Sudoku};
+
+// puzzles of `unsolvable.csv`, which all parse but have no solution
+fn puzzles() -> Vec<Sudoku> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("unsolvable.csv");
+    fs::read_to_string(path)
+        .expect("unsolvable.csv is readable")
+        .lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let puzzle = line.split(';').nth(2).expect("line has a puzzle field");
+            Sudoku::try_from_flattened(puzzle).expect("puzzle parses")
+        })
+        .collect()
+}
+
+// backtracking, forward checking and arc consistency with every pair of the heuristics
+fn methods(slot_heuristics: &[Heuristic], value_heuristics: &[Heuristic]) -> Vec<Method> {
+    let mut methods = Vec::new();
+    for slot_heuristic in slot_heuristics {
+        for value_heuristic in value_heuristics {
+            methods.push(Method::Backtracking {
+                value_heuristic: value_heuristic.clone(),
+                slot_heuristic: slot_heuristic.clone(),
+            });
+            methods.push(Method::ForwardChecking {
+                value_heuristic: value_heuristic.clone(),
+                slot_heuristic: slot_heuristic.clone(),
+            });
+            methods.push(Method::ArcConsistency {
+                value_heuristic: value_heuristic.clone(),
+                slot_heuristic: slot_heuristic.clone(),
+            });
+        }
+    }
+    methods
+}
+
+fn assert_unsolved(puzzles: &[Sudoku], methods: &[Method]) {
+    for method in methods {
+        for (n, puzzle) in puzzles.iter().enumerate() {
+            let mut board = puzzle.clone();
+            board.apply_domain(board.full_domain());
+            let result = solve_seeded(&mut board, method, 7);
+            assert_eq!(
+                result.status,
+                Status::Unsolved,
+                "{:?} solved puzzle {}",
+                method,
+                n + 1
+            );
+        }
+    }
+}
+
+#[test]
+fn no_method_solves_an_unsolvable_puzzle() {
+    let puzzles = puzzles();
+    assert_eq!(puzzles.len(), 5);
+
+    let mut methods = methods(
+        &[
+            Heuristic::Greedy,
+            Heuristic::MostConstrainedVariable,
+            Heuristic::MostConstrainedVariableDegree,
+            Heuristic::DomainOverWeightedDegree,
+        ],
+        &[Heuristic::Greedy, Heuristic::Random],
+    );
+    methods.push(Method::DancingLinks);
+    methods.push(Method::Sat);
+    assert_unsolved(&puzzles, &methods);
+}
+
+// the other orders may leave the cells giving the contradiction away for last and go through most
+// of the board first, so they only run on the first two puzzles, where a cell has no value from
+// the start or once a single other one is filled
+#[test]
+fn no_heuristic_solves_a_contradiction() {
+    let puzzles = puzzles();
+
+    let methods = methods(
+        &[
+            Heuristic::LeastConstrainedVariable,
+            Heuristic::Degree,
+            Heuristic::Random,
+            Heuristic::Reverse,
+        ],
+        &[
+            Heuristic::Greedy,
+            Heuristic::Random,
+            Heuristic::Reverse,
+            Heuristic::LeastConstrainingValue,
+        ],
+    );
+    assert_unsolved(&puzzles[..2], &methods);
+}